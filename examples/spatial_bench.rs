@@ -0,0 +1,128 @@
+use std::time::Instant;
+use rand::{Rng, SeedableRng};
+use rand::rngs::SmallRng;
+use rusty_raid::world::*;
+
+// compares the spatial grid and barnes-hut tree against checking everything against everything
+// cargo run --release --example spatial_bench [level]
+
+const STEPS: usize = 600;
+const QUERIES: usize = 20000;
+
+fn main() {
+    let level = std::env::args().nth(1).map(|l| l.parse::<usize>().expect("level must be a number")).unwrap_or(3);
+
+    let mut path = std::path::PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+    path.push("resources");
+    let mesh_r = World::load_mesh_r(&path).expect("Unable to load meshes");
+
+    let mut w = World::new(mesh_r, 1);
+    w.barnes_hut = false;
+    let _ = w.gen_level(level);
+    w.build_grav_grid();
+    w.build_col_grid();
+    println!("level {}: {} gravity sources, {} colliders, {} collides",
+        level, w.c_grav.len(), w.c_collider.len(), w.c_collides.len());
+
+    // gravity at random points, the two should agree
+    let mut rng = SmallRng::seed_from_u64(2);
+    let pts: Vec<(f64, f64)> = (0..QUERIES).map(
+        |_| (rng.gen_range(-7000.0, 7000.0), rng.gen_range(-7000.0, 7000.0))
+    ).collect();
+
+    let t = Instant::now();
+    let mut brute = Vec::new();
+    for (x, y) in &pts {
+        brute.push(World::get_grav_a(&w.c_grav, &w.c_pos, None, *x, *y, &IdVal::NONE));
+    }
+    let brute_t = t.elapsed();
+
+    let t = Instant::now();
+    let mut grid = Vec::new();
+    for (x, y) in &pts {
+        grid.push(World::get_grav_a(&w.c_grav, &w.c_pos, Some(&w.grav_grid), *x, *y, &IdVal::NONE));
+    }
+    let grid_t = t.elapsed();
+
+    let mut worst: f64 = 0.0;
+    for (b, g) in brute.iter().zip(grid.iter()) {
+        assert_eq!(b.2, g.2, "grid found a different number of gravity sources");
+        worst = worst.max((b.0 - g.0).abs()).max((b.1 - g.1).abs());
+    }
+    println!("gravity x{}: brute {:?}, grid {:?}, largest difference {:e}", QUERIES, brute_t, grid_t, worst);
+
+    // barnes-hut against summing every source with no cutoff
+    w.barnes_hut = true;
+    w.build_grav_grid();
+    let t = Instant::now();
+    let mut exact = Vec::new();
+    for (x, y) in &pts {
+        let mut ax = 0.0;
+        let mut ay = 0.0;
+        let mut total = 0.0; // sum of the size of every pull, what the error is measured against
+        for (id, g) in &w.c_grav {
+            let dx = w.c_pos[id].x - x;
+            let dy = w.c_pos[id].y - y;
+            let r2 = (dx * dx) + (dy * dy);
+            ax += g.mass * dx / (r2 * r2.sqrt());
+            ay += g.mass * dy / (r2 * r2.sqrt());
+            total += g.mass / r2;
+        }
+        exact.push((ax, ay, total));
+    }
+    let exact_t = t.elapsed();
+
+    let t = Instant::now();
+    let mut tree = Vec::new();
+    for (x, y) in &pts {
+        tree.push(w.grav_tree.accel(*x, *y, &IdVal::NONE));
+    }
+    let tree_t = t.elapsed();
+
+    let mut worst: f64 = 0.0;
+    for (e, b) in exact.iter().zip(tree.iter()) {
+        let dx = e.0 - b.0;
+        let dy = e.1 - b.1;
+        worst = worst.max(((dx * dx) + (dy * dy)).sqrt() / e.2);
+    }
+    println!("no cutoff gravity x{}: exact {:?}, barnes-hut theta {} {:?}, largest error {:.4} of the total pull",
+        QUERIES, exact_t, w.grav_tree.theta, tree_t, worst);
+
+    // raycasts between random points, only solid things block
+    let t = Instant::now();
+    let brute: Vec<Option<RayHit>> = pts.windows(2).map(
+        |p| World::raycast(&w.c_collider, &w.c_pos, None, p[0].0, p[0].1, p[1].0, p[1].1, World::blocks_sight)
+    ).collect();
+    let brute_t = t.elapsed();
+
+    let t = Instant::now();
+    let grid: Vec<Option<RayHit>> = pts.windows(2).map(
+        |p| World::raycast(&w.c_collider, &w.c_pos, Some(&w.col_grid), p[0].0, p[0].1, p[1].0, p[1].1, World::blocks_sight)
+    ).collect();
+    let grid_t = t.elapsed();
+
+    let mut blocked = 0;
+    for (b, g) in brute.iter().zip(grid.iter()) {
+        assert_eq!(b.as_ref().map(|h| h.id), g.as_ref().map(|h| h.id), "grid raycast hit something else");
+        if b.is_some() {
+            blocked += 1;
+        }
+    }
+    println!("raycast x{}: brute {:?}, grid {:?}, {} blocked", QUERIES - 1, brute_t, grid_t, blocked);
+
+    // whole steps, thrusting at the portal
+    for (use_grid, barnes_hut) in [(false, false), (true, false), (true, true)].iter() {
+        let mut w = World::new(w.mesh_r.clone(), 1);
+        w.use_grid = *use_grid;
+        w.barnes_hut = *barnes_hut;
+        let _ = w.gen_level(level);
+        w.input.rmb = true;
+
+        let t = Instant::now();
+        for i in 0..STEPS {
+            w.input.lmb = i % 60 == 0;
+            w.step(SIM_DT);
+        }
+        println!("{} steps, grid {}, barnes-hut {}: {:?}, {} entities left", STEPS, use_grid, barnes_hut, t.elapsed(), w.entities.len());
+    }
+}
@@ -0,0 +1,266 @@
+use crate::entity::IdVal;
+
+// barnes-hut quadtree over the gravity sources
+// far away groups of stars get summed up as one mass at their center, so every star pulls
+// without a cutoff radius and the cost stays around n log n
+
+pub const BH_THETA: f64 = 0.3; // opening angle, 0 is exact and bigger is faster but rougher
+const BH_MAX_DEPTH: usize = 24; // stop splitting here, bodies on top of each other share a leaf
+
+struct Body {
+    id: IdVal,
+    x: f64,
+    y: f64,
+    mass: f64,
+}
+
+struct BhNode {
+    cx: f64, // center and half width of the square
+    cy: f64,
+    half: f64,
+    mass: f64, // total mass and center of mass of everything below
+    mx: f64,
+    my: f64,
+    children: Option<[usize; 4]>,
+    bodies: Vec<Body>, // only leaves have bodies
+}
+
+impl BhNode {
+    fn new(cx: f64, cy: f64, half: f64) -> BhNode {
+        BhNode{
+            cx,
+            cy,
+            half,
+            mass: 0.0,
+            mx: 0.0,
+            my: 0.0,
+            children: None,
+            bodies: Vec::new(),
+        }
+    }
+
+    fn quadrant(&self, x: f64, y: f64) -> usize {
+        let mut q = 0;
+        if x >= self.cx {
+            q += 1;
+        }
+        if y >= self.cy {
+            q += 2;
+        }
+        return q;
+    }
+}
+
+pub struct BhTree {
+    nodes: Vec<BhNode>,
+    pub theta: f64,
+}
+
+impl BhTree {
+    pub fn new(theta: f64) -> BhTree {
+        BhTree{
+            nodes: Vec::new(),
+            theta,
+        }
+    }
+
+    // throw away the old tree and build one over these (id, x, y, mass)
+    pub fn build<I: Iterator<Item=(IdVal, f64, f64, f64)>>(&mut self, sources: I) {
+        self.nodes.clear();
+
+        let bodies: Vec<Body> = sources.map(
+            |(id, x, y, mass)| Body{id, x, y, mass}
+        ).collect();
+        if bodies.len() == 0 {
+            return;
+        }
+
+        let mut x1 = std::f64::INFINITY;
+        let mut y1 = std::f64::INFINITY;
+        let mut x2 = std::f64::NEG_INFINITY;
+        let mut y2 = std::f64::NEG_INFINITY;
+        for b in &bodies {
+            x1 = x1.min(b.x);
+            y1 = y1.min(b.y);
+            x2 = x2.max(b.x);
+            y2 = y2.max(b.y);
+        }
+        let half = ((x2 - x1).max(y2 - y1) / 2.0) + 1.0;
+        self.nodes.push(BhNode::new((x1 + x2) / 2.0, (y1 + y2) / 2.0, half));
+
+        for b in bodies {
+            self.insert(0, b, 0);
+        }
+    }
+
+    fn insert(&mut self, n: usize, b: Body, depth: usize) {
+        {
+            let node = &mut self.nodes[n];
+            let m = node.mass + b.mass;
+            if m != 0.0 {
+                node.mx = ((node.mx * node.mass) + (b.x * b.mass)) / m;
+                node.my = ((node.my * node.mass) + (b.y * b.mass)) / m;
+            }
+            node.mass = m;
+        }
+
+        if self.nodes[n].children.is_none() {
+            if self.nodes[n].bodies.len() == 0 || depth >= BH_MAX_DEPTH {
+                self.nodes[n].bodies.push(b);
+                return;
+            }
+
+            // split this leaf and push what was here down a level
+            let (cx, cy, h) = (self.nodes[n].cx, self.nodes[n].cy, self.nodes[n].half / 2.0);
+            let first = self.nodes.len();
+            self.nodes.push(BhNode::new(cx - h, cy - h, h));
+            self.nodes.push(BhNode::new(cx + h, cy - h, h));
+            self.nodes.push(BhNode::new(cx - h, cy + h, h));
+            self.nodes.push(BhNode::new(cx + h, cy + h, h));
+            self.nodes[n].children = Some([first, first + 1, first + 2, first + 3]);
+
+            let old = std::mem::replace(&mut self.nodes[n].bodies, Vec::new());
+            for ob in old {
+                let q = self.nodes[n].quadrant(ob.x, ob.y);
+                self.insert(first + q, ob, depth + 1);
+            }
+        }
+
+        let q = self.nodes[n].quadrant(b.x, b.y);
+        let c = self.nodes[n].children.unwrap()[q];
+        self.insert(c, b, depth + 1);
+    }
+
+    // gravity at a point, ignoring the source id, and how many bodies or groups were summed
+    pub fn accel(&self, px: f64, py: f64, id: &IdVal) -> (f64, f64, usize) {
+        let mut ax = 0.0;
+        let mut ay = 0.0;
+        let mut count = 0;
+
+        if self.nodes.len() == 0 {
+            return (ax, ay, count);
+        }
+
+        let mut pull = |x: f64, y: f64, mass: f64| {
+            let dx = x - px;
+            let dy = y - py;
+            if dx == 0.0 && dy == 0.0 {
+                return;
+            }
+            let r2 = (dx * dx) + (dy * dy);
+            let ga = mass / (r2 * r2.sqrt());
+            ax += ga * dx;
+            ay += ga * dy;
+            count += 1;
+        };
+
+        let mut stack = Vec::with_capacity(64);
+        stack.push(0);
+        while let Some(n) = stack.pop() {
+            let node = &self.nodes[n];
+            if node.mass == 0.0 {
+                continue;
+            }
+
+            match node.children {
+                None => {
+                    for b in &node.bodies {
+                        if b.id != *id {
+                            pull(b.x, b.y, b.mass);
+                        }
+                    }
+                },
+                Some(children) => {
+                    // far enough away that the whole square looks like one point
+                    // pad by how far the center of mass is off center, a big star in a corner needs it
+                    let dx = node.mx - px;
+                    let dy = node.my - py;
+                    let ox = node.mx - node.cx;
+                    let oy = node.my - node.cy;
+                    let open = ((node.half * 2.0) / self.theta) + ((ox * ox) + (oy * oy)).sqrt();
+                    let inside = (px - node.cx).abs() <= node.half && (py - node.cy).abs() <= node.half;
+                    if !inside && (open * open) < ((dx * dx) + (dy * dy)) {
+                        pull(node.mx, node.my, node.mass);
+                    } else {
+                        stack.extend_from_slice(&children);
+                    }
+                },
+            }
+        }
+
+        return (ax, ay, count);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::Rng;
+    use crate::rng::SimRng;
+
+    // a cluster of big stars in the middle and small ones scattered round, like a zone
+    fn stars(rng: &mut SimRng) -> Vec<(IdVal, f64, f64, f64)> {
+        return (0..400).map(|i| {
+            let (d, mass) = if i < 5 { (3000.0, 1e9) } else { (20000.0, rng.gen_range(1e6, 1e8)) };
+            (IdVal{index: i as u32, gen: 0}, rng.gen_range(-d, d), rng.gen_range(-d, d), mass)
+        }).collect();
+    }
+
+    // the pull from every star one by one, and how big all of them are added up without cancelling
+    fn direct(bodies: &[(IdVal, f64, f64, f64)], px: f64, py: f64, id: &IdVal) -> (f64, f64, f64) {
+        let (mut ax, mut ay, mut total) = (0.0, 0.0, 0.0);
+        for (bid, x, y, mass) in bodies {
+            let (dx, dy) = (x - px, y - py);
+            if bid == id || (dx == 0.0 && dy == 0.0) {
+                continue;
+            }
+            let r2 = (dx * dx) + (dy * dy);
+            ax += mass * dx / (r2 * r2.sqrt());
+            ay += mass * dy / (r2 * r2.sqrt());
+            total += mass / r2;
+        }
+        return (ax, ay, total);
+    }
+
+    // worst error over a lot of points, as a fraction of the pulls there
+    // (not of what they add up to, between stars that can cancel out to next to nothing)
+    fn worst_error(theta: f64) -> (f64, usize) {
+        let mut rng = SimRng::new(3);
+        let bodies = stars(&mut rng);
+        let mut t = BhTree::new(theta);
+        t.build(bodies.iter().cloned());
+
+        // at each star, ignoring itself, and at points between them
+        let none = IdVal{index: std::u32::MAX, gen: 0};
+        let mut points: Vec<(f64, f64, IdVal)> = bodies.iter().map(|(id, x, y, _)| (*x, *y, *id)).collect();
+        for _ in 0..400 {
+            points.push((rng.gen_range(-25000.0, 25000.0), rng.gen_range(-25000.0, 25000.0), none));
+        }
+
+        let mut worst: f64 = 0.0;
+        let mut fewest = std::usize::MAX;
+        for (px, py, id) in points {
+            let (ax, ay, count) = t.accel(px, py, &id);
+            let (dx, dy, total) = direct(&bodies, px, py, &id);
+            worst = worst.max(((ax - dx).powi(2) + (ay - dy).powi(2)).sqrt() / total);
+            fewest = fewest.min(count);
+        }
+        return (worst, fewest);
+    }
+
+    #[test]
+    fn theta_zero_is_direct_summation() {
+        let (err, fewest) = worst_error(0.0);
+        // every star on its own, only the order they're added in differs
+        assert!(fewest >= 399);
+        assert!(err < 1e-12, "off by {}", err);
+    }
+
+    #[test]
+    fn default_theta_is_close_to_direct_summation() {
+        let (err, fewest) = worst_error(BH_THETA);
+        assert!(fewest < 399, "it never grouped any stars");
+        // groups are only their total mass, so a percent or so off where it's worst
+        assert!(err < 0.02, "off by {}", err);
+    }
+}
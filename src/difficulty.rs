@@ -0,0 +1,208 @@
+use std::collections::BTreeMap;
+use serde::{Deserialize, Serialize};
+use crate::util::bad_data;
+use crate::prefab::Prefabs;
+
+// how zones get harder the deeper you go, read from resources/difficulty.toml
+// there is a table for each preset and each number in it is a curve over the zone, either
+//
+//   turrets = { start = 1.0, per_zone = 2.0, limit = 25.0 }    start + per_zone * zone, stopping at limit
+//   fuel = [90, 45, 30, 22, 18, 15]                           one for each zone, the last one carrying on
+//
+// counts are rounded down, the rest multiply what the prefabs and generators would have used
+
+const BUILTIN: &str = include_str!("../resources/difficulty.toml");
+
+#[derive(Deserialize, Clone, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct Line {
+    pub start: f64,
+    pub per_zone: f64,
+    pub limit: f64,
+}
+
+#[derive(Deserialize, Clone, Debug)]
+#[serde(untagged)]
+pub enum Curve {
+    Table(Vec<f64>),
+    Line(Line),
+}
+
+impl Curve {
+    pub fn at(&self, level: usize) -> f64 {
+        match self {
+            Curve::Table(t) => return t[level.min(t.len() - 1)],
+            Curve::Line(l) => {
+                let v = l.start + (l.per_zone * (level as f64));
+                // the limit is a ceiling going up and a floor going down
+                if l.per_zone >= 0.0 {
+                    return v.min(l.limit);
+                } else {
+                    return v.max(l.limit);
+                }
+            },
+        }
+    }
+
+    pub fn count(&self, level: usize) -> usize {
+        return self.at(level).floor() as usize;
+    }
+
+    // every value it can take has to be 0 or more, or above 0 if positive
+    fn validate(&self, name: &str, positive: bool) -> Result<(), String> {
+        let vals = match self {
+            Curve::Table(t) if t.is_empty() => return Err(format!("{} has an empty list", name)),
+            Curve::Table(t) => t.clone(),
+            Curve::Line(l) if !l.per_zone.is_finite() => return Err(format!("{} has per_zone {}", name, l.per_zone)),
+            Curve::Line(l) => vec![l.start, l.limit],
+        };
+        for v in vals {
+            if !v.is_finite() || v < 0.0 || (positive && v == 0.0) {
+                return Err(format!("{} has {}, it has to be {}", name, v, if positive { "above 0" } else { "0 or more" }));
+            }
+        }
+        return Ok(());
+    }
+}
+
+#[derive(Deserialize, Clone, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct Difficulty {
+    #[serde(default)]
+    pub lives: Option<usize>, // ships for the run, left out for as many as it takes
+    pub turrets: Curve,
+    pub fuel: Curve, // pickups
+    pub locks: Curve,
+    pub star_density: Curve, // times the stars a generator would put down
+    pub turret_fire_rate: Curve, // times how often turrets shoot
+    pub nuke_thrust: Curve, // times the thrust of turret nukes
+    pub turret_skill: Curve, // times how well turrets lead the player, 1 and up is dead on
+    pub turret_kinds: BTreeMap<String, Curve>, // how likely each turret prefab is, against the others
+    pub turret_orbit: Curve, // chance a turret goes on a moon round a big star
+    pub enemies: Curve, // fighters flying round the zone
+    pub bosses: Curve, // capital ships guarding the portal
+}
+
+impl Difficulty {
+    fn validate(&self) -> Result<(), String> {
+        self.turrets.validate("turrets", false)?;
+        self.fuel.validate("fuel", false)?;
+        self.locks.validate("locks", false)?;
+        self.star_density.validate("star_density", false)?;
+        self.turret_fire_rate.validate("turret_fire_rate", true)?;
+        self.nuke_thrust.validate("nuke_thrust", false)?;
+        self.turret_skill.validate("turret_skill", false)?;
+        for (name, c) in &self.turret_kinds {
+            c.validate(&format!("turret_kinds {}", name), false)?;
+        }
+        self.turret_orbit.validate("turret_orbit", false)?;
+        self.enemies.validate("enemies", false)?;
+        self.bosses.validate("bosses", false)?;
+
+        if self.lives == Some(0) {
+            return Err("lives is 0, leave it out for no limit".to_string());
+        }
+
+        // a zone with no locks opens straight away, and the curve only goes between these
+        let lowest = match &self.locks {
+            Curve::Table(t) => t.iter().cloned().fold(std::f64::INFINITY, f64::min),
+            Curve::Line(l) => l.start.min(l.limit),
+        };
+        if lowest < 1.0 {
+            return Err("locks has to be 1 or more in every zone".to_string());
+        }
+        return Ok(());
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Debug)]
+#[serde(rename_all = "lowercase")]
+pub enum Preset {
+    Easy,
+    Normal,
+    Hard,
+}
+
+impl Preset {
+    pub const ALL: [Preset; 3] = [Preset::Easy, Preset::Normal, Preset::Hard];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Preset::Easy => "easy",
+            Preset::Normal => "normal",
+            Preset::Hard => "hard",
+        }
+    }
+
+    pub fn from_name(s: &str) -> Option<Preset> {
+        return Preset::ALL.iter().copied().find(|p| p.name() == s);
+    }
+}
+
+#[derive(Deserialize, Clone, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct Difficulties {
+    pub easy: Difficulty,
+    pub normal: Difficulty,
+    pub hard: Difficulty,
+}
+
+impl Difficulties {
+    pub fn builtin() -> Difficulties {
+        return Difficulties::parse(BUILTIN).expect("bad builtin difficulty");
+    }
+
+    pub fn get(&self, p: Preset) -> &Difficulty {
+        match p {
+            Preset::Easy => &self.easy,
+            Preset::Normal => &self.normal,
+            Preset::Hard => &self.hard,
+        }
+    }
+
+    pub fn parse(text: &str) -> std::io::Result<Difficulties> {
+        let d: Difficulties = toml::from_str(text).map_err(|e| bad_data(format!("difficulty: {}", e)))?;
+        for p in Preset::ALL.iter() {
+            d.get(*p).validate().map_err(|e| bad_data(format!("difficulty {}: {}", p.name(), e)))?;
+        }
+        return Ok(d);
+    }
+
+    pub fn load(path: &std::path::Path) -> std::io::Result<Difficulties> {
+        return Difficulties::parse(&std::fs::read_to_string(path)?);
+    }
+
+    // the turret kinds name prefabs, once they're all in every one has to be there and be a turret
+    pub fn check(&self, prefabs: &Prefabs) -> std::io::Result<()> {
+        for p in Preset::ALL.iter() {
+            for name in self.get(*p).turret_kinds.keys() {
+                match prefabs.get(name) {
+                    Some(pf) if pf.turret.is_some() => {},
+                    Some(_) => return Err(bad_data(format!("difficulty {}: turret_kinds {} isn't a turret", p.name(), name))),
+                    None => return Err(bad_data(format!("difficulty {}: turret_kinds {}, there's no prefab by that name", p.name(), name))),
+                }
+            }
+        }
+        return Ok(());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn builtin_turret_kinds_are_turrets() {
+        assert!(Difficulties::builtin().check(&Prefabs::builtin()).is_ok());
+    }
+
+    #[test]
+    fn turret_kinds_that_arent_turrets_are_rejected() {
+        let prefabs = Prefabs::builtin();
+        for name in ["lock", "no_such_prefab"].iter() {
+            let mut d = Difficulties::builtin();
+            d.hard.turret_kinds.insert(name.to_string(), Curve::Table(vec![1.0]));
+            assert!(d.check(&prefabs).is_err(), "{} was let through", name);
+        }
+    }
+}
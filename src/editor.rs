@@ -0,0 +1,249 @@
+use crate::world::{World, MeshNum, SIM_DT};
+use crate::prefab::Prefab;
+use crate::level::{LevelFile, LStar, LTurret, LShip, Place};
+
+// level editing, the layout lives in a LevelFile and the world is rebuilt from it after every change
+// physics stays paused, the world only runs the player's prediction so you can see the path from the spawn
+
+pub const EDIT_STAR_SIZE: f64 = 200.0; // size of a newly placed star
+pub const EDIT_STAR_MIN: f64 = 20.0;
+pub const EDIT_STAR_MAX: f64 = 3000.0;
+pub const EDIT_TURRETS: [&str; 4] = ["turret", "flak_turret", "mine_turret", "beam_turret"]; // what T goes through
+pub const EDIT_SHIPS: [&str; 2] = ["fighter", "capital"]; // and for ships
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Kind {
+    Star,
+    BigStar, // a star that always pulls
+    Turret,
+    Lock,
+    Fuel,
+    Portal,
+    Spawn,
+    Ship, // an enemy ship
+}
+
+impl Kind {
+    pub const ALL: [Kind; 8] = [Kind::Star, Kind::BigStar, Kind::Turret, Kind::Lock, Kind::Fuel, Kind::Portal, Kind::Spawn, Kind::Ship];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Kind::Star => "star",
+            Kind::BigStar => "big star",
+            Kind::Turret => "turret",
+            Kind::Lock => "lock",
+            Kind::Fuel => "fuel",
+            Kind::Portal => "portal",
+            Kind::Spawn => "spawn",
+            Kind::Ship => "ship",
+        }
+    }
+
+    fn prefab(&self) -> &'static str {
+        match self {
+            Kind::Star => "star",
+            Kind::BigStar => "big_star",
+            Kind::Turret => "turret",
+            Kind::Lock => "lock",
+            Kind::Fuel => "fuel",
+            Kind::Portal => "portal",
+            Kind::Spawn => "player",
+            Kind::Ship => EDIT_SHIPS[0],
+        }
+    }
+}
+
+// one thing in the layout, stars of both kinds are in level.star
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Pick {
+    Star(usize),
+    Turret(usize),
+    Lock(usize),
+    Fuel(usize),
+    Ship(usize),
+    Portal,
+    Spawn,
+}
+
+pub struct Editor {
+    pub level: LevelFile,
+    pub tool: Kind, // what a click on empty space places
+    pub held: Option<(Pick, f64, f64)>, // being dragged, and where it was grabbed from its center
+    pub dirty: bool, // the world needs rebuilding
+}
+
+impl Editor {
+    pub fn new(level: LevelFile) -> Editor {
+        Editor{
+            level,
+            tool: Kind::Star,
+            held: None,
+            dirty: true,
+        }
+    }
+
+    // how big a prefab's mesh is, for clicking on it
+    fn mesh_rad(w: &World, p: Option<&Prefab>) -> f64 {
+        let i = p.and_then(|p| p.mesh.as_ref()).and_then(|m| crate::world::mesh_index(m));
+        match i {
+            Some(i) => w.mesh_r[i] as f64,
+            None => w.mesh_r[MeshNum::AngMesh as usize] as f64,
+        }
+    }
+
+    fn rad(&self, w: &World, pick: Pick) -> f64 {
+        let kind = match pick {
+            Pick::Star(i) => return self.level.star[i].size,
+            Pick::Ship(i) => return Editor::mesh_rad(w, w.prefabs.get(&self.level.ship[i].prefab)),
+            Pick::Turret(_) => Kind::Turret,
+            Pick::Lock(_) => Kind::Lock,
+            Pick::Fuel(_) => Kind::Fuel,
+            Pick::Portal => Kind::Portal,
+            Pick::Spawn => Kind::Spawn,
+        };
+        return Editor::mesh_rad(w, w.prefabs.get(kind.prefab()));
+    }
+
+    pub fn pos(&self, pick: Pick) -> (f64, f64) {
+        let l = &self.level;
+        match pick {
+            Pick::Star(i) => (l.star[i].x, l.star[i].y),
+            Pick::Turret(i) => (l.turret[i].x, l.turret[i].y),
+            Pick::Lock(i) => (l.lock[i].x, l.lock[i].y),
+            Pick::Fuel(i) => (l.fuel[i].x, l.fuel[i].y),
+            Pick::Ship(i) => (l.ship[i].x, l.ship[i].y),
+            Pick::Portal => (l.portal.x, l.portal.y),
+            Pick::Spawn => (l.spawn.x, l.spawn.y),
+        }
+    }
+
+    fn set_pos(&mut self, pick: Pick, x: f64, y: f64) {
+        let l = &mut self.level;
+        match pick {
+            Pick::Star(i) => { l.star[i].x = x; l.star[i].y = y; },
+            Pick::Turret(i) => { l.turret[i].x = x; l.turret[i].y = y; },
+            Pick::Lock(i) => { l.lock[i].x = x; l.lock[i].y = y; },
+            Pick::Fuel(i) => { l.fuel[i].x = x; l.fuel[i].y = y; },
+            Pick::Ship(i) => { l.ship[i].x = x; l.ship[i].y = y; },
+            Pick::Portal => { l.portal.x = x; l.portal.y = y; },
+            Pick::Spawn => { l.spawn.x = x; l.spawn.y = y; },
+        }
+        self.dirty = true;
+    }
+
+    // the thing under a point, small things first so they can be grabbed off the top of a star
+    pub fn pick(&self, w: &World, x: f64, y: f64) -> Option<Pick> {
+        let l = &self.level;
+        let mut picks = vec![Pick::Spawn, Pick::Portal];
+        picks.extend((0..l.fuel.len()).map(Pick::Fuel));
+        picks.extend((0..l.ship.len()).map(Pick::Ship));
+        picks.extend((0..l.lock.len()).map(Pick::Lock));
+        picks.extend((0..l.turret.len()).map(Pick::Turret));
+        picks.extend((0..l.star.len()).map(Pick::Star));
+
+        for p in picks {
+            let (px, py) = self.pos(p);
+            let r = self.rad(w, p);
+            let dx = px - x;
+            let dy = py - y;
+            if (dx * dx) + (dy * dy) <= (r * r) {
+                return Some(p);
+            }
+        }
+        return None;
+    }
+
+    // grab what is under the point, or put down a new one of the tool there and grab that
+    pub fn press(&mut self, w: &World, x: f64, y: f64) {
+        let pick = match self.pick(w, x, y) {
+            Some(p) => p,
+            None => {
+                let p = Place{x, y, a: 0.0};
+                let l = &mut self.level;
+                let pick = match self.tool {
+                    Kind::Star | Kind::BigStar => {
+                        l.star.push(LStar{x, y, size: EDIT_STAR_SIZE, always_pull: self.tool == Kind::BigStar});
+                        Pick::Star(l.star.len() - 1)
+                    },
+                    Kind::Turret => {
+                        l.turret.push(LTurret{x, y, a: 0.0, prefab: EDIT_TURRETS[0].to_string(), orbit: None});
+                        Pick::Turret(l.turret.len() - 1)
+                    },
+                    Kind::Lock => { l.lock.push(p); Pick::Lock(l.lock.len() - 1) },
+                    Kind::Fuel => { l.fuel.push(p); Pick::Fuel(l.fuel.len() - 1) },
+                    Kind::Ship => {
+                        l.ship.push(LShip{x, y, prefab: EDIT_SHIPS[0].to_string()});
+                        Pick::Ship(l.ship.len() - 1)
+                    },
+                    Kind::Portal => Pick::Portal,
+                    Kind::Spawn => Pick::Spawn,
+                };
+                self.set_pos(pick, x, y);
+                pick
+            },
+        };
+
+        let (px, py) = self.pos(pick);
+        self.held = Some((pick, x - px, y - py));
+    }
+
+    pub fn drag(&mut self, x: f64, y: f64) {
+        if let Some((pick, ox, oy)) = self.held {
+            if self.pos(pick) != (x - ox, y - oy) {
+                self.set_pos(pick, x - ox, y - oy);
+            }
+        }
+    }
+
+    pub fn release(&mut self) {
+        self.held = None;
+    }
+
+    // the portal and spawn always have to be somewhere, so they don't delete
+    pub fn delete(&mut self, w: &World, x: f64, y: f64) {
+        let pick = self.pick(w, x, y);
+        let l = &mut self.level;
+        match pick {
+            Some(Pick::Star(i)) => { l.star.remove(i); },
+            Some(Pick::Turret(i)) => { l.turret.remove(i); },
+            Some(Pick::Lock(i)) => { l.lock.remove(i); },
+            Some(Pick::Fuel(i)) => { l.fuel.remove(i); },
+            Some(Pick::Ship(i)) => { l.ship.remove(i); },
+            _ => return,
+        }
+        self.held = None;
+        self.dirty = true;
+    }
+
+    // scale the star under the point
+    pub fn resize(&mut self, w: &World, x: f64, y: f64, by: f64) {
+        if let Some(Pick::Star(i)) = self.pick(w, x, y) {
+            let s = &mut self.level.star[i];
+            s.size = (s.size * by).max(EDIT_STAR_MIN).min(EDIT_STAR_MAX);
+            self.dirty = true;
+        }
+    }
+
+    // the turret or ship under the point becomes the next kind in EDIT_TURRETS or EDIT_SHIPS
+    pub fn cycle_kind(&mut self, w: &World, x: f64, y: f64) {
+        let (prefab, kinds) = match self.pick(w, x, y) {
+            Some(Pick::Turret(i)) => (&mut self.level.turret[i].prefab, &EDIT_TURRETS[..]),
+            Some(Pick::Ship(i)) => (&mut self.level.ship[i].prefab, &EDIT_SHIPS[..]),
+            _ => return,
+        };
+        let next = kinds.iter().position(|n| n == prefab).map_or(0, |k| (k + 1) % kinds.len());
+        *prefab = kinds[next].to_string();
+        self.dirty = true;
+    }
+
+    // put the layout in the world and show where the player would fall from the spawn
+    pub fn rebuild(&mut self, w: &mut World) {
+        if !self.dirty {
+            return;
+        }
+        let level = w.level;
+        w.load_level(&self.level, level);
+        w.preview(SIM_DT);
+        self.dirty = false;
+    }
+}
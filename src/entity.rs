@@ -0,0 +1,214 @@
+use serde::{Deserialize, Serialize};
+
+// entity ids and the allocator that hands them out
+// an id is a slot index plus the generation of that slot, so when a slot gets reused
+// anything still holding the old id (a rocket's target, a trail's object) no longer matches
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Debug, Serialize, Deserialize)]
+pub struct IdVal {
+    pub index: u32,
+    pub gen: u32,
+}
+
+impl IdVal {
+    // never handed out, for asking about a point that isn't any entity
+    pub const NONE: IdVal = IdVal{index: std::u32::MAX, gen: 0};
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+struct Slot {
+    gen: u32,
+    alive: bool,
+    to_destroy: bool,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct Entities {
+    slots: Vec<Slot>,
+    free: Vec<u32>, // dead slots to reuse, last freed goes first
+    marked: Vec<IdVal>, // waiting for s_destroy
+    count: usize,
+}
+
+impl Entities {
+    pub fn new() -> Entities {
+        Entities{
+            slots: Vec::new(),
+            free: Vec::new(),
+            marked: Vec::new(),
+            count: 0,
+        }
+    }
+
+    // free everything, new ids start over from the first slot
+    // but the slots keep counting generations, so an id from before doesn't match whatever gets its slot
+    pub fn clear(&mut self) {
+        for s in &mut self.slots {
+            if s.alive {
+                s.gen = s.gen.wrapping_add(1);
+            }
+            s.alive = false;
+            s.to_destroy = false;
+        }
+        self.free = (0..(self.slots.len() as u32)).rev().collect();
+        self.marked.clear();
+        self.count = 0;
+    }
+
+    pub fn spawn(&mut self) -> IdVal {
+        self.count += 1;
+        if let Some(index) = self.free.pop() {
+            let s = &mut self.slots[index as usize];
+            s.alive = true;
+            s.to_destroy = false;
+            return IdVal{index, gen: s.gen};
+        }
+
+        let index = self.slots.len() as u32;
+        self.slots.push(
+            Slot{
+                gen: 0,
+                alive: true,
+                to_destroy: false,
+            }
+        );
+        return IdVal{index, gen: 0};
+    }
+
+    pub fn is_alive(&self, id: &IdVal) -> bool {
+        match self.slots.get(id.index as usize) {
+            Some(s) => s.alive && s.gen == id.gen,
+            None => false,
+        }
+    }
+
+    // mark for destruction at the end of the step
+    // false if the id is stale or already marked
+    pub fn destroy(&mut self, id: &IdVal) -> bool {
+        if !self.is_alive(id) {
+            return false;
+        }
+        let s = &mut self.slots[id.index as usize];
+        if s.to_destroy {
+            return false;
+        }
+        s.to_destroy = true;
+        self.marked.push(*id);
+        return true;
+    }
+
+    pub fn is_marked(&self, id: &IdVal) -> bool {
+        return self.is_alive(id) && self.slots[id.index as usize].to_destroy;
+    }
+
+    // everything marked since the last call, in the order it was marked
+    pub fn take_marked(&mut self) -> Vec<IdVal> {
+        return std::mem::replace(&mut self.marked, Vec::new());
+    }
+
+    // the slot is dead and the next id out of it gets a new generation
+    pub fn free(&mut self, id: &IdVal) {
+        if !self.is_alive(id) {
+            return;
+        }
+        let s = &mut self.slots[id.index as usize];
+        s.alive = false;
+        s.to_destroy = false;
+        s.gen = s.gen.wrapping_add(1);
+        self.free.push(id.index);
+        self.count -= 1;
+    }
+
+    pub fn len(&self) -> usize {
+        return self.count;
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item=IdVal> + '_ {
+        return self.slots.iter().enumerate().filter(|(_, s)| s.alive).map(
+            |(i, s)| IdVal{index: i as u32, gen: s.gen}
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn spawn_destroy_free() {
+        let mut e = Entities::new();
+        let a = e.spawn();
+        let b = e.spawn();
+        assert_eq!(e.len(), 2);
+        assert!(e.is_alive(&a));
+
+        // marked is still alive until s_destroy frees it
+        assert!(e.destroy(&a));
+        assert!(e.is_marked(&a));
+        assert!(e.is_alive(&a));
+        e.free(&a);
+        assert!(!e.is_alive(&a));
+        assert!(e.is_alive(&b));
+        assert_eq!(e.len(), 1);
+        assert_eq!(e.iter().collect::<Vec<_>>(), vec![b]);
+    }
+
+    #[test]
+    fn respawn_reuses_slot_with_next_gen() {
+        let mut e = Entities::new();
+        let a = e.spawn();
+        e.destroy(&a);
+        e.free(&a);
+
+        let c = e.spawn();
+        assert_eq!(c.index, a.index);
+        assert_eq!(c.gen, a.gen + 1);
+        assert!(e.is_alive(&c));
+
+        // the old id is stale now the slot belongs to something else
+        assert!(!e.is_alive(&a));
+        assert!(!e.destroy(&a));
+        assert!(!e.is_marked(&c));
+    }
+
+    #[test]
+    fn clear_keeps_generations() {
+        let mut e = Entities::new();
+        let a = e.spawn();
+        let b = e.spawn();
+        e.clear();
+        assert_eq!(e.len(), 0);
+        assert!(!e.is_alive(&a));
+
+        // the slots are handed out again from the first, each a generation on
+        let c = e.spawn();
+        let d = e.spawn();
+        assert_eq!((c.index, d.index), (a.index, b.index));
+        assert!(!e.is_alive(&a));
+        assert!(!e.is_alive(&b));
+        assert!(!e.destroy(&a));
+        assert!(e.is_alive(&c) && e.is_alive(&d));
+    }
+
+    #[test]
+    fn destroy_twice_is_false() {
+        let mut e = Entities::new();
+        let a = e.spawn();
+        assert!(e.destroy(&a));
+        assert!(!e.destroy(&a));
+        assert_eq!(e.take_marked(), vec![a]);
+    }
+
+    #[test]
+    fn take_marked_drains() {
+        let mut e = Entities::new();
+        let a = e.spawn();
+        let b = e.spawn();
+        let c = e.spawn();
+        e.destroy(&c);
+        e.destroy(&a);
+        assert_eq!(e.take_marked(), vec![c, a]);
+        assert!(e.take_marked().is_empty());
+        assert!(e.is_alive(&b));
+    }
+}
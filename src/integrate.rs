@@ -0,0 +1,88 @@
+// integrators for moving bodies through the gravity field
+// s_move and s_predict both go through here so the drawn prediction follows the same math as the ship
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Integrator {
+    Euler, // semi-implicit euler, cheap but orbits drift
+    Verlet, // velocity verlet (leapfrog), conserves energy over long flights
+    Rk4, // classic runge-kutta, most accurate per step but not symplectic
+}
+
+impl Integrator {
+    pub fn parse(s: &str) -> Option<Integrator> {
+        match s {
+            "euler" => Some(Integrator::Euler),
+            "verlet" => Some(Integrator::Verlet),
+            "rk4" => Some(Integrator::Rk4),
+            _ => None,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Integrator::Euler => "euler",
+            Integrator::Verlet => "verlet",
+            Integrator::Rk4 => "rk4",
+        }
+    }
+}
+
+// one step of dt for a body at x, y with velocity vx, vy
+// accel gives the gravity at a point, in_ax and in_ay are held constant over the step (thrust)
+// returns the new x, y, vx, vy
+pub fn integrate<F: Fn(f64, f64) -> (f64, f64)>(
+    integ: Integrator,
+    accel: F,
+    x: f64, y: f64,
+    vx: f64, vy: f64,
+    in_ax: f64, in_ay: f64,
+    dt: f64,
+) -> (f64, f64, f64, f64) {
+    let acc = |px: f64, py: f64| {
+        let (ax, ay) = accel(px, py);
+        (ax + in_ax, ay + in_ay)
+    };
+
+    match integ {
+        Integrator::Euler => {
+            let (ax, ay) = acc(x, y);
+            let vx = vx + (ax * dt);
+            let vy = vy + (ay * dt);
+            return (x + (vx * dt), y + (vy * dt), vx, vy);
+        },
+        Integrator::Verlet => {
+            // kick, drift, kick
+            let h = dt * 0.5;
+            let (ax, ay) = acc(x, y);
+            let hvx = vx + (ax * h);
+            let hvy = vy + (ay * h);
+            let nx = x + (hvx * dt);
+            let ny = y + (hvy * dt);
+            let (ax, ay) = acc(nx, ny);
+            return (nx, ny, hvx + (ax * h), hvy + (ay * h));
+        },
+        Integrator::Rk4 => {
+            let h = dt * 0.5;
+
+            let (k1ax, k1ay) = acc(x, y);
+            let (k1vx, k1vy) = (vx, vy);
+
+            let (k2vx, k2vy) = (vx + (k1ax * h), vy + (k1ay * h));
+            let (k2ax, k2ay) = acc(x + (k1vx * h), y + (k1vy * h));
+
+            let (k3vx, k3vy) = (vx + (k2ax * h), vy + (k2ay * h));
+            let (k3ax, k3ay) = acc(x + (k2vx * h), y + (k2vy * h));
+
+            let (k4vx, k4vy) = (vx + (k3ax * dt), vy + (k3ay * dt));
+            let (k4ax, k4ay) = acc(x + (k3vx * dt), y + (k3vy * dt));
+
+            let s = dt / 6.0;
+            return (
+                x + (s * (k1vx + (2.0 * k2vx) + (2.0 * k3vx) + k4vx)),
+                y + (s * (k1vy + (2.0 * k2vy) + (2.0 * k3vy) + k4vy)),
+                vx + (s * (k1ax + (2.0 * k2ax) + (2.0 * k3ax) + k4ax)),
+                vy + (s * (k1ay + (2.0 * k2ay) + (2.0 * k3ay) + k4ay)),
+            );
+        },
+    }
+}
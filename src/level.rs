@@ -0,0 +1,180 @@
+use serde::{Deserialize, Serialize};
+use crate::world::{World, CollisionType};
+use crate::util::bad_data;
+
+// hand made zone layouts, a toml file listing where everything goes
+//
+//   portal = { x = 0.0, y = 0.0, a = 0.0 }
+//   spawn = { x = 6000.0, y = 0.0 }
+//   [[star]]
+//   x = 1200.0
+//   y = -300.0
+//   size = 400.0
+//   always_pull = true
+//   [[lock]] has x, y, a and [[fuel]] has x, y
+//   [[turret]] has x, y, a, the prefab if it isn't a plain turret, and an orbit if it goes round on a moon
+//   x = 2000.0
+//   y = 0.0
+//   prefab = "flak_turret"
+//   orbit = { x = 1200.0, y = 0.0, rate = 0.1, moon = 120.0 }   round x, y at rate radians a second
+//   [[ship]] has x, y and the prefab of an enemy ship, which patrols round there
+//   x = -2500.0
+//   y = 800.0
+//   prefab = "fighter"
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct Place {
+    pub x: f64,
+    pub y: f64,
+    #[serde(default)]
+    pub a: f32,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct LStar {
+    pub x: f64,
+    pub y: f64,
+    pub size: f64,
+    #[serde(default)]
+    pub always_pull: bool,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct LOrbit {
+    pub x: f64,
+    pub y: f64,
+    pub rate: f64,
+    pub moon: f64, // size of the moon it stands on
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct LTurret {
+    pub x: f64,
+    pub y: f64,
+    #[serde(default)]
+    pub a: f32,
+    #[serde(default = "plain_turret", skip_serializing_if = "is_plain_turret")]
+    pub prefab: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub orbit: Option<LOrbit>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct LShip {
+    pub x: f64,
+    pub y: f64,
+    pub prefab: String,
+}
+
+fn plain_turret() -> String {
+    return "turret".to_string();
+}
+
+fn is_plain_turret(p: &str) -> bool {
+    return p == "turret";
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct LevelFile {
+    pub portal: Place,
+    pub spawn: Place,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub star: Vec<LStar>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub turret: Vec<LTurret>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub lock: Vec<Place>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub fuel: Vec<Place>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub ship: Vec<LShip>,
+}
+
+impl LevelFile {
+    pub fn parse(text: &str) -> std::io::Result<LevelFile> {
+        let l: LevelFile = toml::from_str(text).map_err(|e| bad_data(format!("level: {}", e)))?;
+        for s in &l.star {
+            if !(s.size > 0.0) {
+                return Err(bad_data(format!("level: star at {}, {} has size {}, it has to be above 0", s.x, s.y, s.size)));
+            }
+        }
+        return Ok(l);
+    }
+
+    pub fn load(path: &std::path::Path) -> std::io::Result<LevelFile> {
+        return LevelFile::parse(&std::fs::read_to_string(path)?);
+    }
+
+    pub fn save(&self, path: &std::path::Path) -> std::io::Result<()> {
+        return std::fs::write(path, self.to_string());
+    }
+
+    // what is in the world right now, so a generated zone can be saved and edited
+    pub fn from_world(w: &World) -> LevelFile {
+        let place = |id| {
+            let p = &w.c_pos[id];
+            Place{x: p.x, y: p.y, a: p.a}
+        };
+
+        // sorted by id so the file comes out in the order things were made
+        let mut ids: Vec<_> = w.c_pos.keys().collect();
+        ids.sort();
+
+        let mut l = LevelFile{
+            portal: w.portal.as_ref().map(place).unwrap_or(Place{x: 0.0, y: 0.0, a: 0.0}),
+            spawn: Place{x: w.spawn.0, y: w.spawn.1, a: 0.0},
+            star: Vec::new(),
+            turret: Vec::new(),
+            lock: Vec::new(),
+            fuel: Vec::new(),
+            ship: Vec::new(),
+        };
+
+        for id in ids {
+            if let Some(t) = w.c_turret.get(id) {
+                let p = &w.c_pos[id];
+                let orbit = w.c_orbit.get(id).map(|o| LOrbit{
+                    x: o.x,
+                    y: o.y,
+                    rate: o.rate,
+                    moon: o.moon.and_then(|m| w.c_collider.get(&m)).map_or(0.0, |c| c.rad),
+                });
+                l.turret.push(LTurret{x: p.x, y: p.y, a: p.a, prefab: t.name.clone(), orbit});
+            } else if let Some(pl) = w.c_pilot.get(id) {
+                l.ship.push(LShip{x: pl.home.0, y: pl.home.1, prefab: pl.name.clone()});
+            } else if w.locks.contains(id) {
+                l.lock.push(place(id));
+            } else if let Some(c) = w.c_collider.get(id) {
+                match c.col_action {
+                    CollisionType::FuelPup(_) => l.fuel.push(place(id)),
+                    _ if c.stop_col && w.c_grav.contains_key(id) => {
+                        let p = &w.c_pos[id];
+                        l.star.push(
+                            LStar{
+                                x: p.x,
+                                y: p.y,
+                                size: c.rad,
+                                always_pull: !w.c_grav[id].dist2.is_finite(),
+                            }
+                        );
+                    },
+                    _ => (),
+                }
+            }
+        }
+
+        return l;
+    }
+}
+
+impl std::fmt::Display for LevelFile {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "{}", toml::to_string(self).expect("level always serializes"))
+    }
+}
@@ -0,0 +1,661 @@
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use crate::world::{World, GravField, IdVal, CollisionType, MeshNum, MOUNT_GAP, level_seed};
+use crate::integrate::integrate;
+
+// generated zones
+// each kind of zone is a LevelGenerator that lays out the stars its own way and puts the rest where there is room
+// the first zone is always the scattered one, after that the run seed picks one for each zone
+//
+// making sure a generated zone can be finished
+// every placement gets a fixed number of tries, and once everything is down a ship is flown from the
+// spawn to each lock and to the portal under the same gravity the game uses, a zone where one of them
+// can't be reached is thrown away and generated again from another seed
+
+pub const GEN_PLACE_TRIES: usize = 300; // random spots tried for one thing before giving up on it
+pub const GEN_LAYOUTS: usize = 12; // layouts tried for a zone before giving up on it
+
+pub const REACH_DT: f64 = 1.0 / 20.0;
+pub const REACH_TIME: f64 = 150.0; // seconds a flight gets to arrive
+pub const REACH_SPEEDS: [f64; 3] = [120.0, 240.0, 480.0]; // cruising speeds tried, slow ones stay out of trouble
+pub const REACH_GAIN: f64 = 1.5; // how hard the pilot corrects towards the speed it wants
+pub const REACH_SLACK: f64 = 0.2; // of the speed it wants, how far off it drifts before burning
+pub const REACH_LOOKAHEAD: f64 = 4.0; // seconds ahead the pilot looks for stars in the way
+pub const REACH_SAMPLES: usize = 4; // points along the look ahead checked for pull
+pub const REACH_DANGER: f64 = 0.6; // pull past this much of the ship's thrust is somewhere it can't get out of
+pub const REACH_DETOUR: f64 = 0.4; // how far out to the side a detour goes, of the distance to the target
+pub const REACH_WAYPOINT: f64 = 500.0; // how close it has to get to a detour's turning point
+pub const REACH_LOCK_DIST: f64 = 600.0; // close enough to a lock to hit it with a nuke
+pub const ORBIT_TRIES: usize = 6; // orbits tried for a turret before it goes somewhere still instead
+pub const ORBIT_GAP: f64 = 150.0; // clear space either side of the ring a moon sweeps
+pub const ENEMY_TRIES: usize = 6; // spots tried for an enemy ship before leaving it out
+pub const ENEMY_GAP: f64 = 200.0; // clear space round where an enemy ship starts
+pub const ENEMY_CLEAR: f64 = 3000.0; // enemy ships start at least this far from the spawn
+
+// why a layout was thrown away
+#[derive(Clone, Debug)]
+pub enum GenFail {
+    NoRoom(&'static str), // nowhere left to put one of these
+    Unreachable(&'static str, f64, f64), // no flight from the spawn got to the one at x, y
+}
+
+impl std::fmt::Display for GenFail {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            GenFail::NoRoom(what) => write!(f, "there was no room for a {}", what),
+            GenFail::Unreachable(what, x, y) => write!(f, "the {} at {:.0}, {:.0} couldn't be reached", what, x, y),
+        }
+    }
+}
+
+// every layout tried for a zone failed, the world keeps the closest one so there is still something to play
+#[derive(Clone, Debug)]
+pub struct GenError {
+    pub level: usize,
+    pub layouts: usize,
+    pub last: GenFail,
+}
+
+impl std::fmt::Display for GenError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "zone {} had no solvable layout in {} tries, in the last one {}", self.level + 1, self.layouts, self.last)
+    }
+}
+
+// colours a zone is drawn in
+#[derive(Serialize, Deserialize, Clone, Copy, Debug)]
+pub struct Palette {
+    pub background: [f32; 4],
+    pub star: [f32; 4],
+    pub big_star: [f32; 4], // stars that always pull
+}
+
+impl Default for Palette {
+    fn default() -> Palette {
+        Palette{
+            background: [0.0, 0.0, 0.0, 1.0],
+            star: [1.0; 4],
+            big_star: [1.0; 4],
+        }
+    }
+}
+
+pub trait LevelGenerator {
+    fn name(&self) -> &'static str;
+    fn palette(&self) -> Palette;
+    // fill the empty world with one layout, random from w.rng
+    fn layout(&self, w: &mut World, level: usize) -> Result<(), GenFail>;
+}
+
+pub const GENERATORS: [&dyn LevelGenerator; 5] = [&Scattered, &Binary, &Belt, &Nebula, &Slingshot];
+
+pub fn generator_for(seed: u64, level: usize) -> &'static dyn LevelGenerator {
+    if level == 0 {
+        return &Scattered;
+    }
+    // by the high half of the zone's seed, the layout gets the whole seed from gen_level
+    return GENERATORS[((level_seed(seed, level) >> 32) as usize) % GENERATORS.len()];
+}
+
+const FULL: (f64, f64) = (0.0, std::f64::consts::PI * 2.0);
+
+// how many of n stars the difficulty wants in this zone
+fn dense(w: &World, level: usize, n: usize) -> usize {
+    return ((n as f64) * w.difficulty().star_density.at(level)).round() as usize;
+}
+
+// turrets, fuel, locks, the spawn and enemy ships, the same in every kind of zone, spread over extent from the portal
+// the spawn goes at the edge between the spawn angles
+// stars turrets fuel and ships that don't fit are left out but a missing lock or spawn makes the layout useless
+// turrets on moons go first, everything after keeps out of the rings they sweep
+pub fn place_common(w: &mut World, level: usize, extent: f64, spawn: (f64, f64)) -> Result<(), GenFail> {
+    let f = extent / 6000.0;
+
+    let turret_r = w.mesh_r[MeshNum::HashMesh as usize] as f64;
+    let turret_amt = w.difficulty().turrets.count(level);
+    let orbit = w.difficulty().turret_orbit.at(level);
+    let mut still = Vec::new();
+    for _ in 0..turret_amt {
+        let name = turret_kind(w, level);
+        if w.rng.gen_range(0.0, 1.0) < orbit && place_orbit_turret(w, &name, turret_r) {
+            continue;
+        }
+        still.push(name);
+    }
+    for name in still {
+        place_turret(w, &name, f, turret_r);
+    }
+
+    let fuel_r = w.mesh_r[MeshNum::HashMesh as usize] as f64;
+    let fuel_amt = w.difficulty().fuel.count(level);
+    for _ in 0..fuel_amt {
+        if let Some((x, y)) = w.gen_spot((450.0 * f, 4500.0 * f), FULL, fuel_r, 1.0) {
+            w.add_fuel_powerup(x, y);
+        }
+    }
+
+    let lock_r = w.mesh_r[MeshNum::HashMesh as usize] as f64;
+    let num_locks = w.difficulty().locks.count(level);
+    for _ in 0..num_locks {
+        let (x, y) = w.gen_spot((300.0 * f, 6000.0 * f), FULL, lock_r, 45.0).ok_or(GenFail::NoRoom("lock"))?;
+        let a = y.atan2(x);
+        w.add_lock(x, y, a as f32);
+    }
+
+    let player_r = w.mesh_r[MeshNum::AngMesh as usize] as f64;
+    let (x, y) = w.gen_spot((5500.0 * f, 6900.0 * f), spawn, player_r, 100.0).ok_or(GenFail::NoRoom("spawn"))?;
+    w.spawn_player(x, y);
+
+    // fighters anywhere the player won't run straight into them, bosses just outside the portal
+    let fighter_r = w.mesh_r[MeshNum::AMesh as usize] as f64;
+    for _ in 0..w.difficulty().enemies.count(level) {
+        for _ in 0..ENEMY_TRIES {
+            if let Some((ex, ey)) = w.gen_spot((900.0 * f, 6000.0 * f), FULL, fighter_r, ENEMY_GAP) {
+                if ((ex - x).powi(2) + (ey - y).powi(2)) > (ENEMY_CLEAR * ENEMY_CLEAR) {
+                    w.add_enemy("fighter", ex, ey);
+                    break;
+                }
+            }
+        }
+    }
+    let boss_r = w.mesh_r[MeshNum::CapMesh as usize] as f64;
+    for _ in 0..w.difficulty().bosses.count(level) {
+        if let Some((ex, ey)) = w.gen_spot((700.0, 700.0 + (900.0 * f)), FULL, boss_r, ENEMY_GAP) {
+            w.add_enemy("capital", ex, ey);
+        }
+    }
+
+    return Ok(());
+}
+
+// for when no layout of a zone had room for its locks and spawn, just the portal and the spawn out
+// in the open, nothing can stop them fitting so there is always a ship to fly
+pub fn place_fallback(w: &mut World) {
+    w.add_portal(0.0, 0.0, 0.0);
+    let a = w.rng.gen_range(FULL.0, FULL.1);
+    w.spawn_player(6000.0 * a.cos(), 6000.0 * a.sin());
+}
+
+// one of the difficulty's turret_kinds by their weights for the zone, a plain turret if they are all 0
+fn turret_kind(w: &mut World, level: usize) -> String {
+    let kinds: Vec<(String, f64)> = w.difficulty().turret_kinds.iter().map(|(k, c)| (k.clone(), c.at(level))).collect();
+    let total: f64 = kinds.iter().map(|(_, v)| v).sum();
+    if total <= 0.0 {
+        return "turret".to_string();
+    }
+    let mut pick = w.rng.gen_range(0.0, total);
+    for (k, v) in &kinds {
+        if pick < *v {
+            return k.clone();
+        }
+        pick -= v;
+    }
+    return kinds.last().unwrap().0.clone();
+}
+
+fn place_turret(w: &mut World, name: &str, f: f64, turret_r: f64) {
+    if let Some((x, y)) = w.gen_spot((900.0 * f, 6600.0 * f), FULL, turret_r, 0.0) {
+        let a = w.rng.gen_range(-std::f32::consts::PI, std::f32::consts::PI);
+        w.add_turret(name, x, y, a);
+    }
+}
+
+// on a moon going round one of the big stars, as fast as something falling round it would,
+// false if there are no big stars or none of the rings tried are clear
+fn place_orbit_turret(w: &mut World, name: &str, turret_r: f64) -> bool {
+    let mut stars: Vec<IdVal> = w.c_grav.iter().filter(|(_, g)| !g.dist2.is_finite()).map(|(id, _)| *id).collect();
+    if stars.is_empty() {
+        return false;
+    }
+    stars.sort();
+
+    for _ in 0..ORBIT_TRIES {
+        let sid = stars[w.rng.gen_range(0, stars.len())];
+        let (cx, cy) = (w.c_pos[&sid].x, w.c_pos[&sid].y);
+        let sr = w.c_collider.get(&sid).map_or(0.0, |c| c.rad);
+        let moon = w.rng.gen_range(80.0, 140.0);
+        let dist = sr + w.rng.gen_range(700.0, 1500.0);
+        let angle = w.rng.gen_range(FULL.0, FULL.1);
+        let dir = if w.rng.gen_range(0.0, 1.0) < 0.5 { -1.0 } else { 1.0 };
+
+        // everything between the inside of the moon and the outside of the turret goes round
+        let inner = dist - turret_r - MOUNT_GAP - (2.0 * moon);
+        let outer = dist + turret_r;
+        if inner - ORBIT_GAP <= sr {
+            continue;
+        }
+        let clear = match ring_clear(w, &sid, cx, cy, inner, outer) {
+            Some(c) => c,
+            None => continue,
+        };
+        for id in clear {
+            w.despawn(&id);
+        }
+
+        let md = dist - (turret_r + MOUNT_GAP + moon);
+        let rate = dir * (w.c_grav[&sid].mass / (md * md * md)).sqrt();
+        return w.add_orbit_turret(name, cx, cy, dist, angle, rate, moon).is_some();
+    }
+    return false;
+}
+
+// the small stars to take out so nothing but the star in the middle comes within ORBIT_GAP of the ring
+// from inner to outer round cx, cy, None if something else is in the way
+fn ring_clear(w: &World, star: &IdVal, cx: f64, cy: f64, inner: f64, outer: f64) -> Option<Vec<IdVal>> {
+    let mut clear = Vec::new();
+    let sized = w.c_collider.iter().map(|(id, c)| (id, c.rad)).chain(w.c_collides.iter().map(|(id, c)| (id, c.rad)));
+    for (id, r) in sized {
+        if id == star {
+            continue;
+        }
+        let p = &w.c_pos[id];
+        let d = ((p.x - cx).powi(2) + (p.y - cy).powi(2)).sqrt();
+        if d + r + ORBIT_GAP > inner && d - r - ORBIT_GAP < outer {
+            let small_star = w.c_grav.get(id).map_or(false, |g| g.dist2.is_finite()) && !w.c_collides.contains_key(id);
+            if !small_star {
+                return None;
+            }
+            clear.push(*id);
+        }
+    }
+    clear.sort();
+    return Some(clear);
+}
+
+// a few big stars in the middle and small ones scattered round them, how every zone used to look
+pub struct Scattered;
+
+impl LevelGenerator for Scattered {
+    fn name(&self) -> &'static str {
+        return "scattered";
+    }
+
+    fn palette(&self) -> Palette {
+        return Palette::default();
+    }
+
+    fn layout(&self, w: &mut World, level: usize) -> Result<(), GenFail> {
+        w.add_portal(0.0, 0.0, 0.0);
+
+        let num_big = w.rng.gen_range(1, 6);
+        for _ in 0..num_big {
+            let s = w.rng.gen_range(300.0, 360.0 + (300.0 / (num_big as f64)));
+            if let Some((x, y)) = w.gen_spot((450.0, 3000.0), FULL, s, 0.0) {
+                w.add_star(x, y, s, true);
+            }
+        }
+
+        let num_small = w.rng.gen_range(45, 180);
+        for _ in 0..dense(w, level, num_small) {
+            let s = w.rng.gen_range(45.0, 120.0);
+            if let Some((x, y)) = w.gen_spot((999.0, 6000.0), FULL, s, 0.0) {
+                w.add_star(x, y, s, false);
+            }
+        }
+
+        return place_common(w, level, 6000.0, FULL);
+    }
+}
+
+// pairs of big stars close together, with the space between them pulled both ways
+pub struct Binary;
+
+impl LevelGenerator for Binary {
+    fn name(&self) -> &'static str {
+        return "binary";
+    }
+
+    fn palette(&self) -> Palette {
+        return Palette{
+            background: [0.06, 0.02, 0.0, 1.0],
+            star: [1.0, 0.85, 0.6, 1.0],
+            big_star: [1.0, 0.55, 0.3, 1.0],
+        };
+    }
+
+    fn layout(&self, w: &mut World, level: usize) -> Result<(), GenFail> {
+        w.add_portal(0.0, 0.0, 0.0);
+
+        let pairs = w.rng.gen_range(1, 4);
+        for _ in 0..pairs {
+            let half = w.rng.gen_range(500.0, 900.0); // from the middle of the pair to each star
+            let s = w.rng.gen_range(220.0, 320.0);
+            let (cx, cy) = match w.gen_spot((1800.0, 4500.0), FULL, half + s, 200.0) {
+                Some(c) => c,
+                None => continue,
+            };
+            let a = w.rng.gen_range(FULL.0, FULL.1);
+            let (dx, dy) = (a.cos() * half, a.sin() * half);
+            let s2 = s * w.rng.gen_range(0.7, 1.0);
+            w.add_star(cx + dx, cy + dy, s, true);
+            w.add_star(cx - dx, cy - dy, s2, true);
+        }
+
+        let num_small = w.rng.gen_range(30, 90);
+        for _ in 0..dense(w, level, num_small) {
+            let s = w.rng.gen_range(45.0, 120.0);
+            if let Some((x, y)) = w.gen_spot((999.0, 6000.0), FULL, s, 0.0) {
+                w.add_star(x, y, s, false);
+            }
+        }
+
+        return place_common(w, level, 6000.0, FULL);
+    }
+}
+
+// rings of small rocks round the portal, each with a gap or two to get through
+pub struct Belt;
+
+impl LevelGenerator for Belt {
+    fn name(&self) -> &'static str {
+        return "belt";
+    }
+
+    fn palette(&self) -> Palette {
+        return Palette{
+            background: [0.0, 0.02, 0.06, 1.0],
+            star: [0.7, 0.72, 0.8, 1.0],
+            big_star: [0.85, 0.9, 1.0, 1.0],
+        };
+    }
+
+    fn layout(&self, w: &mut World, level: usize) -> Result<(), GenFail> {
+        w.add_portal(0.0, 0.0, 0.0);
+
+        let density = w.difficulty().star_density.at(level);
+        let rings = w.rng.gen_range(2, 4);
+        for k in 0..rings {
+            let r = 1500.0 + (1600.0 * (k as f64)) + w.rng.gen_range(-200.0, 200.0);
+            let gaps: Vec<f64> = (0..w.rng.gen_range(1, 3)).map(|_| w.rng.gen_range(FULL.0, FULL.1)).collect();
+            let gap_width = 700.0 / r; // angle
+
+            let mut a: f64 = 0.0;
+            while a < FULL.1 {
+                let s = w.rng.gen_range(30.0, 70.0);
+                let d = r + w.rng.gen_range(-150.0, 150.0);
+                let in_gap = gaps.iter().any(|g| {
+                    let off = (a - g).rem_euclid(FULL.1);
+                    off < gap_width || off > FULL.1 - gap_width
+                });
+                if !in_gap && w.gen_fits(d * a.cos(), d * a.sin(), s, 0.0) {
+                    w.add_star(d * a.cos(), d * a.sin(), s, false);
+                }
+                a += ((s * 2.0) + (w.rng.gen_range(20.0, 120.0) / density.max(0.1))) / r;
+            }
+        }
+
+        // something between the rings to swing round
+        let s = w.rng.gen_range(200.0, 280.0);
+        if let Some((x, y)) = w.gen_spot((2000.0, 4000.0), FULL, s, 300.0) {
+            w.add_star(x, y, s, true);
+        }
+
+        return place_common(w, level, 6000.0, FULL);
+    }
+}
+
+// lots of tiny stars and hardly any big ones, no pull strong enough to trap you but little clear space
+pub struct Nebula;
+
+impl LevelGenerator for Nebula {
+    fn name(&self) -> &'static str {
+        return "nebula";
+    }
+
+    fn palette(&self) -> Palette {
+        return Palette{
+            background: [0.05, 0.0, 0.07, 1.0],
+            star: [0.95, 0.6, 0.95, 1.0],
+            big_star: [0.7, 0.5, 1.0, 1.0],
+        };
+    }
+
+    fn layout(&self, w: &mut World, level: usize) -> Result<(), GenFail> {
+        w.add_portal(0.0, 0.0, 0.0);
+
+        let num_big = w.rng.gen_range(0, 3);
+        for _ in 0..num_big {
+            let s = w.rng.gen_range(200.0, 300.0);
+            if let Some((x, y)) = w.gen_spot((1500.0, 4000.0), FULL, s, 400.0) {
+                w.add_star(x, y, s, true);
+            }
+        }
+
+        let num_small = w.rng.gen_range(250, 400);
+        for _ in 0..dense(w, level, num_small) {
+            let s = w.rng.gen_range(12.0, 35.0);
+            if let Some((x, y)) = w.gen_spot((700.0, 6200.0), FULL, s, 30.0) {
+                w.add_star(x, y, s, false);
+            }
+        }
+
+        return place_common(w, level, 6000.0, FULL);
+    }
+}
+
+// a corridor walled in by small stars running out from the portal, with big stars down the middle
+// to slingshot off, the spawn is at the far end
+pub struct Slingshot;
+
+impl LevelGenerator for Slingshot {
+    fn name(&self) -> &'static str {
+        return "slingshot";
+    }
+
+    fn palette(&self) -> Palette {
+        return Palette{
+            background: [0.0, 0.05, 0.05, 1.0],
+            star: [0.5, 1.0, 0.9, 1.0],
+            big_star: [0.3, 0.9, 1.0, 1.0],
+        };
+    }
+
+    fn layout(&self, w: &mut World, level: usize) -> Result<(), GenFail> {
+        w.add_portal(0.0, 0.0, 0.0);
+
+        let c = w.rng.gen_range(FULL.0, FULL.1);
+        let (ux, uy) = (c.cos(), c.sin()); // along the corridor
+        let (px, py) = (-uy, ux); // across it
+
+        let num_big = w.rng.gen_range(3, 6);
+        for i in 0..num_big {
+            let along = 1200.0 + (3800.0 * (i as f64) / ((num_big - 1) as f64));
+            let side = if i % 2 == 0 { 1.0 } else { -1.0 };
+            let across = side * w.rng.gen_range(500.0, 800.0);
+            let s = w.rng.gen_range(220.0, 300.0);
+            let (x, y) = ((ux * along) + (px * across), (uy * along) + (py * across));
+            if w.gen_fits(x, y, s, 0.0) {
+                w.add_star(x, y, s, true);
+            }
+        }
+
+        let density = w.difficulty().star_density.at(level);
+        for side in [-1.0, 1.0].iter() {
+            let mut along = 600.0;
+            while along < 6000.0 {
+                let s = w.rng.gen_range(50.0, 110.0);
+                let across = side * w.rng.gen_range(1600.0, 2000.0);
+                let (x, y) = ((ux * along) + (px * across), (uy * along) + (py * across));
+                // some missing so it isn't sealed
+                if w.rng.gen_range(0.0, 1.0) > 0.25 && w.gen_fits(x, y, s, 0.0) {
+                    w.add_star(x, y, s, false);
+                }
+                along += (s * 2.0) + (w.rng.gen_range(40.0, 160.0) / density.max(0.1));
+            }
+        }
+
+        return place_common(w, level, 6000.0, (c - 0.25, c + 0.25));
+    }
+}
+
+// the player as it is at the spawn has to be able to get to every lock and to the portal
+// gravity has to be built already
+pub fn check_reach(w: &World) -> Result<(), GenFail> {
+    let pid = match w.playerid {
+        Some(id) => id,
+        None => return Err(GenFail::NoRoom("spawn")),
+    };
+
+    for id in &w.locks {
+        let p = &w.c_pos[id];
+        if !reachable(w, &pid, p.x, p.y, REACH_LOCK_DIST) {
+            return Err(GenFail::Unreachable("lock", p.x, p.y));
+        }
+    }
+
+    if let Some(id) = w.portal {
+        let p = &w.c_pos[&id];
+        let r = w.c_collider.get(&id).map(|c| c.rad).unwrap_or(0.0);
+        if !reachable(w, &pid, p.x, p.y, r) {
+            return Err(GenFail::Unreachable("portal", p.x, p.y));
+        }
+    }
+
+    return Ok(());
+}
+
+// try flying there at each cruising speed, straight or with a detour
+pub fn reachable(w: &World, ship: &IdVal, tx: f64, ty: f64, near: f64) -> bool {
+    let p = &w.c_pos[ship];
+    let dx = tx - p.x;
+    let dy = ty - p.y;
+
+    // straight there, or out to one side first
+    let mut routes = vec![vec![(tx, ty, near)]];
+    for side in [-REACH_DETOUR, REACH_DETOUR].iter() {
+        let wx = p.x + (dx * 0.5) - (dy * side);
+        let wy = p.y + (dy * 0.5) + (dx * side);
+        routes.push(vec![(wx, wy, REACH_WAYPOINT), (tx, ty, near)]);
+    }
+
+    return routes.iter().any(
+        |route| REACH_SPEEDS.iter().any(|speed| fly(w, ship, route, *speed))
+    );
+}
+
+// a simple pilot, heads for each point of the route in turn at a speed, burning only when gravity has pushed it too far off
+// and steering round any star it is about to hit or pull it is about to get stuck in
+// fuel it flies through is picked up, true if it gets within near of the last point before crashing or running out of time
+pub fn fly(w: &World, ship: &IdVal, route: &[(f64, f64, f64)], speed: f64) -> bool {
+    let field = GravField{
+        gravs: &w.c_grav,
+        pos: &w.c_pos,
+        grid: if w.use_grid { Some(&w.grav_grid) } else { None },
+        tree: if w.barnes_hut { Some(&w.grav_tree) } else { None },
+    };
+    let grid = if w.use_grid { Some(&w.col_grid) } else { None };
+
+    let s = &w.c_ship[ship];
+    let p = &w.c_pos[ship];
+    let d = &w.c_dynamic[ship];
+    let (mut x, mut y) = (p.x, p.y);
+    let (mut vx, mut vy) = (d.x_vel, d.y_vel);
+    let mut fuel = s.fuel;
+    let mut taken = Vec::new();
+
+    let mut leg = 0;
+    let mut t = 0.0;
+    while t < REACH_TIME {
+        let (tx, ty, near) = route[leg];
+        let dx = tx - x;
+        let dy = ty - y;
+        let dist = ((dx * dx) + (dy * dy)).sqrt();
+        if dist <= near {
+            leg += 1;
+            if leg == route.len() {
+                return true;
+            }
+            continue;
+        }
+
+        // slow down coming in to the end so it doesn't fly past
+        let want = if leg + 1 == route.len() { speed.min(dist * 0.5) } else { speed };
+        let mut wvx = (dx / dist) * want;
+        let mut wvy = (dy / dist) * want;
+
+        // something ahead to get round, a star in the way or pull too strong to get back out of
+        // looking the way it wants to go until it is moving
+        let (hx, hy) = if ((vx * vx) + (vy * vy)).sqrt() > want * 0.5 { (vx, vy) } else { (wvx, wvy) };
+        let mut avoid = None;
+        let ahead = World::raycast(
+            &w.c_collider, &w.c_pos, grid,
+            x, y,
+            x + (hx * REACH_LOOKAHEAD), y + (hy * REACH_LOOKAHEAD),
+            World::blocks_sight,
+        );
+        if let Some(hit) = ahead {
+            let c = &w.c_pos[&hit.id];
+            avoid = Some((x - c.x, y - c.y));
+        } else {
+            for k in 1..=REACH_SAMPLES {
+                let f = REACH_LOOKAHEAD * (k as f64) / (REACH_SAMPLES as f64);
+                let (gx, gy) = field.accel(x + (hx * f), y + (hy * f), ship);
+                if ((gx * gx) + (gy * gy)).sqrt() > s.thrust * REACH_DANGER {
+                    avoid = Some((-gx, -gy));
+                    break;
+                }
+            }
+        }
+
+        // go round it on whichever side is closer to the way it wants to go, and a bit away from it
+        if let Some((ox, oy)) = avoid {
+            let od = ((ox * ox) + (oy * oy)).sqrt().max(1e-9);
+            let (ux, uy) = (ox / od, oy / od);
+            let (mut rx, mut ry) = (-uy, ux);
+            if (rx * wvx) + (ry * wvy) < 0.0 {
+                rx = -rx;
+                ry = -ry;
+            }
+            wvx = (rx + (ux * 0.5)) * speed;
+            wvy = (ry + (uy * 0.5)) * speed;
+        }
+
+        let mut ax = 0.0;
+        let mut ay = 0.0;
+        let ex = wvx - vx;
+        let ey = wvy - vy;
+        let off = ((ex * ex) + (ey * ey)).sqrt();
+        if off > speed * REACH_SLACK {
+            ax = ex * REACH_GAIN;
+            ay = ey * REACH_GAIN;
+            let most = if fuel > 0.0 { s.thrust } else { s.empty_thrust };
+            let a = off * REACH_GAIN;
+            if a > most {
+                ax *= most / a;
+                ay *= most / a;
+            }
+        }
+        // burns fuel the same way the player does
+        fuel -= (ax + ay).abs() * REACH_DT;
+        if fuel < 0.0 {
+            fuel = 0.0;
+        }
+
+        let accel = |px: f64, py: f64| field.accel(px, py, ship);
+        let (nx, ny, nvx, nvy) = integrate(w.integrator, accel, x, y, vx, vy, ax, ay, REACH_DT);
+
+        let crash = World::raycast(&w.c_collider, &w.c_pos, grid, x, y, nx, ny, World::blocks_sight);
+        if crash.is_some() {
+            return false;
+        }
+
+        let pickup = World::raycast(
+            &w.c_collider, &w.c_pos, grid,
+            x, y, nx, ny,
+            |id, c| matches!(c.col_action, CollisionType::FuelPup(_)) && !taken.contains(id),
+        );
+        if let Some(hit) = pickup {
+            if let CollisionType::FuelPup(amt) = w.c_collider[&hit.id].col_action {
+                fuel += amt;
+            }
+            taken.push(hit.id);
+        }
+
+        x = nx;
+        y = ny;
+        vx = nvx;
+        vy = nvy;
+        t += REACH_DT;
+    }
+    return false;
+}
@@ -0,0 +1,16 @@
+pub mod world;
+pub mod integrate;
+pub mod spatial;
+pub mod barnes_hut;
+pub mod replay;
+pub mod entity;
+pub mod rng;
+pub mod prefab;
+pub mod tuning;
+pub mod level;
+pub mod levelgen;
+pub mod difficulty;
+pub mod editor;
+pub mod score;
+pub mod save;
+pub mod util;
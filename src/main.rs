@@ -1,59 +1,50 @@
 use std::collections::HashMap;
 use ggez::*;
-use rand::{Rng, SeedableRng};
-use rand::rngs::SmallRng;
-use std::io::{BufReader, BufRead};
+use std::io::{BufReader, Read, Write};
+use rand::Rng;
+use rusty_raid::world::*;
+use rusty_raid::replay::Replay;
+use rusty_raid::integrate::Integrator;
+use rusty_raid::barnes_hut::BH_THETA;
+use rusty_raid::tuning::Tuning;
+use rusty_raid::prefab::Prefabs;
+use rusty_raid::level::LevelFile;
+use rusty_raid::editor::{Editor, Kind};
+use rusty_raid::difficulty::{Difficulties, Preset};
+use rusty_raid::score::{HighScores, HighScore, date};
+use rusty_raid::save::Save;
+use rusty_raid::util::TextHash;
 
 // constants
 
 const GAME_NAME: &str = "Falling Carefully";
-const STAR_GRAV_MUL: f64 = 1.5;
 const STAR_RES: f32 = 27.0;
-const STAR_E_SZ: f32 = 1500.0;
-const E_TG: f64 = 0.0018;
-const E_TS: f64 = 0.0012;
-const E_TF: f64 = 0.0009;
 const EXPLOSION_RES: f32 = 10.0;
 const EXPLOSION_STROKE: f32 = 27.0;
-const PORTAL_SCALE: f32 = 300.0;
-const PORTAL_GRAV: f64 = 300.0 * 200.0 * 100.0;
-const SHIP_SCALE: f32 = 18.0;
-const SHIP_TRAIL_SZ: f32 = 3.6;
-const SHIP_TRAIL_AMT: usize = 120;
-const TURRET_SCALE: f32 = 120.0;
-const POWERUP_SCALE: f32 = 100.0;
-const FUEL_PER_PUP: f64 = 450.0;
-const SHIP_MINSZ: f32 = 1.0;    // minimun scale factor it will get to
-const GRAV_REACH: f64 = 0.45;    // minimum pull before out of range
 const MAX_CAM_SCALE: f32 = 90.0;
 const MIN_CAM_SCALE: f32 = 0.201;
 const ZOOM_AMT: f32 = 0.06;
 const LOG_TICKS: usize = 81;
-const PLAYER_THRUST: f64 = 69.0;
-const PLAYER_FUEL: f64 = 1200.0;
-const PLAYER_EMPTY_THRUST: f64 = 12.0;
-const PLAYER_AMMO: usize = 15;
-const PLAYER_NUKE_SIZE: f32 = 900.0;
-const PLAYER_NUKE_IVEL: f64 = 750.0;
-const PLAYER_NUKE_DIST: f64 = 18.0;
-const PLAYER_NUKE_THRUST: f64 = 15.0;
-const NUKE_TRAIL_SZ: f32 = 2.0;
 const EXPLOSION_COLOR: [f32; 4] = [1.0, 0.12, 0.27, 0.9];
-const SHIP_TRAIL_COLOR: [f32; 4] = [1.0, 0.81, 0.90, 0.69];
-const NUKE_TRAIL_COLOR: [f32; 4] = [1.0, 0.0, 0.0, 0.6];
-const PRED_COLOR: [f32; 4] = [0.6, 0.75, 1.0, 0.69];
 const PRED_SIZE: f32 = 0.81;
-const PRED_RATE: f64 = 0.0;
-const PRED_TSTEP: f64 = 0.27;
-const PRED_AMT: usize = 45;
-const TRAIL_DIST: f32 = 30.0;
-const TURRET_FIRE_RATE: f64 = 1.8;
-const TURRET_UPDATE_RATE: f64 = 0.45;
-const TURRET_NUKE_THRUST: f64 = 180.0;
-const TURRET_NUKE_SIZE: f32 = 270.0;
-const TURRET_NUKE_DIST: f64 = 60.0;
-const TURRET_NUKE_IVEL: f64 = 300.0;
-const TURRET_DIST2: f64 = 8000.0 * 8000.0;
+const BEAM_SIZE: f32 = 3.0; // screen pixels, when that is wider than BEAM_MIN in the world
+const BEAM_MIN: f32 = 12.0;
+const BEAM_COLOR: [f32; 4] = [1.0, 0.9, 0.45, 0.85];
+const MAX_STEPS: usize = 8; // most physics steps to catch up on in one frame
+const EDITOR_PAN: f64 = 900.0; // screen pixels a second the camera moves in the editor
+const EDITOR_RESIZE: f64 = 1.1;
+const EDITOR_HELP: &str = concat!(
+    "editor (tab to play)\n",
+    "1-8 pick what to place, left click to place or drag\n",
+    "right click deletes, [ ] resize stars\n",
+    "T changes the kind of turret or ship\n",
+    "wasd or arrows move, F2 saves\n",
+);
+const DEATH_CAM_RATE: f64 = 3.0; // how fast the camera closes on where the ship blew up, per second
+const DEATH_CAM_SCALE: f32 = 0.6; // and the zoom it settles on
+const DATA_CHECK_RATE: f64 = 0.5; // seconds between looking for edited tuning or prefab files
+const HIGH_SCORE_FILE: &str = "/highscores.txt"; // in the user data dir
+const SAVE_FILE: &str = "/save.toml"; // the run in progress, also in the user data dir
 
 const GUIDE: &str = concat!(
     "      Welcome to Falling Carefully\n",
@@ -62,6 +53,11 @@ const GUIDE: &str = concat!(
     "    =>     =  Nuke (Left Click to Release)\n",
     "     *     =  Fuel\n",
     "     #     =  Enemy Turret\n",
+    "     ^     =  Flak Turret (Shells Burst Near You)\n",
+    "     %     =  Mine Layer (Keep Clear of the o)\n",
+    "     I     =  Beam Turret (Wears Down Your Hull)\n",
+    "     A     =  Enemy Fighter (Flies Like You Do)\n",
+    "   @=<E    =  Capital Ship (Takes a Few Hits)\n",
     "     &     =  Portal Lock (Destroy These)\n",
     "\n",
     "   ( X )   =  Closed portal\n",
@@ -74,145 +70,6 @@ const GUIDE: &str = concat!(
     "       Press R to Start / Restart\n",
 );
 
-type IdVal = usize;
-
-struct Entity {
-    id: IdVal,
-    to_destroy: bool,
-}
-
-struct CPos {
-    x: f64,
-    y: f64,
-    a: f32,
-}
-
-struct CGrav {
-    mass: f64,
-    dist2: f64,  // distance squared at which this object can be ignored
-}
-
-struct CDynamic {
-    x_vel: f64,
-    y_vel: f64,
-    //a_vel: f32,
-    in_ax: f64,
-    in_ay: f64,
-}
-
-struct CTrail {
-    objid: IdVal,
-    pts: Vec<[f32; 2]>, // x, y, thrust
-    max_len: usize,
-    size: f32, // width of the trail
-    color: [f32; 4],
-    dist: f32,
-}
-
-// Used for predictions on movement
-struct CPredictable {
-    objid: IdVal,
-    pts: Vec<[f32; 2]>,
-    tstep: f64,
-    rate: f64,
-    till_next: f64,
-    valid_len: usize,
-    collidable: bool,
-    color: [f32; 4],
-}
-
-enum CollisionType {
-    Explosion(f32, bool),
-    FuelPup(f64),
-    Portal,
-    None,
-}
-
-struct CCollider {
-    rad: f64,
-    col_action: CollisionType,
-    stop_col: bool,
-}
-
-struct CCollides {
-    rad: f64,
-}
-
-struct CExplosion {
-    grow_size: f32,
-    time_grow: f64,
-    time_stay: f64,
-    time_fade: f64,
-    time_so_far: f64,
-}
-
-struct CRocket {
-    thrust: f64,
-    target: Option<IdVal>,
-}
-
-struct CTurret {
-    fire_rate: f64,
-    till_next_shot: f64,
-}
-
-enum DrawThing {
-    Blank,
-    Mesh(graphics::Mesh),
-    MeshScale(graphics::Mesh, f32),
-    MeshInd(usize),
-}
-
-struct CDrawable {
-    thing: DrawThing,
-    r: f32, // radius for culling
-    minsz: f32,
-}
-
-impl CDrawable {
-    fn draw(&self, st: &State, ctx: &mut Context, mut param: graphics::DrawParam) -> error::GameResult {
-        if self.minsz != 0.0 && st.cam.s < self.minsz {
-            let s = self.minsz / st.cam.s;
-            param = param.scale([s, s]);
-        }
-        match self.thing {
-            DrawThing::Blank => return Ok(()),
-            DrawThing::Mesh(ref m) => {
-                return graphics::draw(ctx, m, param);
-            },
-            DrawThing::MeshScale(ref m, sc) => {
-                return graphics::draw(ctx, m, param.scale([sc,sc]));
-            },
-            DrawThing::MeshInd(i) => {
-                let (m, _) = &st.meshs[i];
-                return graphics::draw(ctx, m, param);
-            },
-        }
-    }
-}
-
-struct CShip {
-    thrust: f64, // fake thrust actually, pure accelaration, no mass
-    empty_thrust: f64, // small amount of thrust when no fuel is available
-    fuel: f64,
-    ammo: usize,
-}
-
-struct InputState {
-    up: bool,
-    down: bool,
-    right: bool,
-    left: bool,
-    cw: bool,
-    ccw: bool,
-    reset: bool,
-
-    mx: f32,
-    my: f32,
-    lmb: bool,
-    rmb: bool,
-}
-
 struct Camera {
     x: f64,
     y: f64,
@@ -285,75 +142,74 @@ impl Camera {
     } 
 }
 
-enum MeshNum {
-    AngMesh = 0,
-    AMesh,
-    AstMesh,
-    BangVMesh,
-    CapMesh,
-    HashMesh,
-    NukeMesh,
-    LockMesh,
-    ClosedMesh,
-    OpenMesh,
-}
-
 struct State {
     //shaders: Vec<graphics::Shader>,
-    meshs: Vec<(graphics::Mesh, f32)>,
-    rng: SmallRng,
+    meshs: Vec<graphics::Mesh>,
+    circles: HashMap<IdVal, (f32, bool, [f32; 4], graphics::Mesh)>, // star and explosion meshes, by entity
     cam: Camera,
-    level: usize,
-    next_id: IdVal,
-    entities: Vec<Entity>,
-    c_pos: HashMap<IdVal, CPos>,
-    c_grav: HashMap<IdVal, CGrav>,
-    c_dynamic: HashMap<IdVal, CDynamic>,
-    c_collider: HashMap<IdVal, CCollider>,
-    c_collides: HashMap<IdVal, CCollides>,
-    c_drawable: HashMap<IdVal, CDrawable>,
-    c_trail: HashMap<IdVal, CTrail>,
-    c_predictable: HashMap<IdVal, CPredictable>,
-    c_ship: HashMap<IdVal, CShip>,
-    c_explosion: HashMap<IdVal, CExplosion>,
-    c_rocket: HashMap<IdVal, CRocket>,
-    c_turret: HashMap<IdVal, CTurret>,
-
-    locks: Vec<IdVal>,
-    portal: Option<IdVal>,
-
-    s_turret_next: f64,
-
-    input: InputState,
-    playerid: Option<IdVal>,
-    finished: bool, // finished level
-    started: bool,
+    world: World,
+    data: DataWatch,
+    acc: f64, // time not yet simulated
+    fixed_seed: bool, // keep the seed on restart instead of rolling a new one
+    recording: Option<Replay>,
+    replaying: Option<Replay>, // inputs come from here instead of events
+    editor: Option<Editor>, // editing the zone, physics is paused
+    high_scores: HighScores,
+    new_high: Option<usize>, // place in the table of the run that just ended
+
+    // mouse position in screen space
+    mx: f32,
+    my: f32,
 
     font: graphics::Font,
 
     //DEBUG
     //log_time: usize,
-    //grav_count: usize,
 }
 
 impl State {
-    fn new(ctx: &mut Context) -> ggez::GameResult<State> {
+    fn new(ctx: &mut Context, res_dir: std::path::PathBuf, seed: Option<u64>, integrator: Integrator, barnes_hut: Option<f64>, record: bool, replaying: Option<Replay>, levels: Vec<(String, LevelFile)>) -> ggez::GameResult<State> {
+        let mut meshs = Vec::new();
+        let mut mesh_r = Vec::new();
+        for (p, scale, color) in MESHES.iter() {
+            let (m, r) = load_mesh(ctx, p, *scale, *color);
+            meshs.push(m);
+            mesh_r.push(r);
+        }
+
+        let seed = match replaying {
+            Some(ref r) => Some(r.seed),
+            None => seed,
+        };
+        let fixed_seed = seed.is_some();
+        let seed = seed.unwrap_or_else(|| rand::thread_rng().gen());
+        let (integrator, barnes_hut) = match replaying {
+            Some(ref r) => (r.integrator, r.barnes_hut),
+            None => (integrator, barnes_hut),
+        };
+
+        let mut world = World::new(mesh_r, seed);
+        let mut data = DataWatch{
+            dir: res_dir,
+            stamp: None,
+            next_check: DATA_CHECK_RATE,
+        };
+        data.stamp = data.newest();
+        if let Err(e) = data.load(&mut world) {
+            panic!("{}", e);
+        }
+        world.integrator = integrator;
+        world.barnes_hut = barnes_hut.is_some();
+        if let Some(theta) = barnes_hut {
+            world.grav_tree.theta = theta;
+        }
+        let level_paths: Vec<String> = levels.iter().map(|(p, _)| p.clone()).collect();
+        world.levels = levels.into_iter().map(|(_, l)| l).collect();
+        let data_hash = world.data_hash;
+
         let s = State{
-            meshs: [
-                    ("\\ang.obj", SHIP_SCALE, [1.0; 4]),
-                    ("\\A.obj", SHIP_SCALE, [1.0; 4]),
-                    ("\\ast.obj", POWERUP_SCALE, [0.75, 0.75, 0.81, 1.0]),
-                    ("\\bangv.obj", POWERUP_SCALE, [0.75, 0.75, 1.0, 1.0]),
-                    ("\\capital.obj", SHIP_SCALE, [1.0; 4]),
-                    ("\\pnd.obj", TURRET_SCALE, [0.9, 0.48, 0.45, 1.0]),
-                    ("\\nuke.obj", SHIP_SCALE, [0.81, 0.3, 0.3, 1.0]),
-                    ("\\lock.obj", POWERUP_SCALE, [0.5, 0.5, 0.69, 1.0]),
-                    ("\\ClosedPortal.obj", PORTAL_SCALE, [0.6, 0.6, 0.81, 1.0]),
-                    ("\\OpenPortal.obj", PORTAL_SCALE, [0.3, 0.42, 0.9, 1.0]),
-                ].iter().map(
-                |x| load_mesh(ctx, x.0, x.1, x.2)
-            ).collect(),
-            rng: SmallRng::from_entropy(),
+            meshs,
+            circles: HashMap::new(),
 
             cam: Camera{
                 x: 0.0,
@@ -362,1330 +218,358 @@ impl State {
                 update: true,
             },
 
-            level: 0,
+            world,
+            data,
+            acc: 0.0,
+            fixed_seed,
+            recording: if record {
+                let mut r = Replay::new(seed, integrator, barnes_hut);
+                r.levels = level_paths;
+                r.data = Some(data_hash);
+                Some(r)
+            } else {
+                None
+            },
+            replaying,
+            editor: None,
+            high_scores: load_high_scores(ctx),
+            new_high: None,
+
+            mx: 0.0,
+            my: 0.0,
 
             font: graphics::Font::new(ctx, "\\NovaMono-Regular.ttf").unwrap(),
 
             //DEBUG
             //log_time: 0,
-            //grav_count: 0,
-
-            input: InputState{
-                up: false,
-                down: false,
-                left: false,
-                right: false,
-                cw: false,
-                ccw: false,
-                reset: false,
-                mx: 0.0,
-                my: 0.0,
-                lmb: false,
-                rmb: false,
-            },
-
-            // items that change between levels /etc
-            next_id: 1,
-            entities: Vec::new(),
-            c_pos: HashMap::new(),
-            c_grav: HashMap::new(),
-            c_dynamic: HashMap::new(),
-            c_collider: HashMap::new(),
-            c_collides: HashMap::new(),
-            c_drawable: HashMap::new(),
-            c_trail: HashMap::new(),
-            c_predictable: HashMap::new(),
-            c_ship: HashMap::new(),
-            c_explosion: HashMap::new(),
-            c_rocket: HashMap::new(),
-            c_turret: HashMap::new(),
-
-            locks: Vec::new(),
-            portal: None,
-
-            s_turret_next: 0.0,
-
-            playerid: None,
-            finished: false,
-            started: false,
-
         };
 
         Ok(s)
     }
 
-    fn reset(&mut self) {
-        self.entities.clear();
-        self.c_pos.clear();
-        self.c_grav.clear();
-        self.c_dynamic.clear();
-        self.c_collider.clear();
-        self.c_collides.clear();
-        self.c_drawable.clear();
-        self.c_trail.clear();
-        self.c_predictable.clear();
-        self.c_ship.clear();
-        self.c_explosion.clear();
-        self.c_rocket.clear();
-        self.c_turret.clear();
-        self.locks.clear();
-        self.portal = None;
-        self.s_turret_next = 0.0;
-        self.playerid = None;
-        self.level = 0;
-        self.finished = false;
-        self.started = true;
-    }
-
-    fn s_destroy(&mut self) {
-        let mut i = 0;
-        while i < self.entities.len() {
-            let e = &self.entities[i];
-            if !e.to_destroy {
-                i += 1;
-                continue;
-            }
-
-            if let Some(pid) = self.playerid {
-                if pid == e.id {
-                    self.playerid = None;
-                }
-            }
-
-            let mut j = 0;
-            while j < self.locks.len() {
-                if self.locks[j] == e.id {
-                    self.locks.remove(j);
-                } else {
-                    j += 1;
-                }
-            }
-            if self.locks.len() == 0 {
-                //open portal
-                if let Some(portalid) = &self.portal {
-                    let d = self.c_drawable.get_mut(portalid).unwrap();
-                    let c = self.c_collider.get_mut(portalid).unwrap();
-
-                    c.col_action = CollisionType::Portal;
-                    d.thing = DrawThing::MeshInd(MeshNum::OpenMesh as usize);
-                }
-            }
-
-            self.c_pos.remove(&e.id);
-            self.c_grav.remove(&e.id);
-            self.c_dynamic.remove(&e.id);
-            self.c_collider.remove(&e.id);
-            self.c_collides.remove(&e.id);
-            self.c_drawable.remove(&e.id);
-            self.c_predictable.remove(&e.id);
-            self.c_ship.remove(&e.id);
-            self.c_trail.remove(&e.id);
-            self.c_explosion.remove(&e.id);
-            self.c_rocket.remove(&e.id);
-            self.c_turret.remove(&e.id);
-
-            self.entities.remove(i);
-        }
-    }
-
-    fn gen_level(&mut self, ctx: &mut Context, level: usize) {
-        self.reset();
-        self.level = level;
-        //TODO make this good
-        //need portal, key, turrets, boosters
-
-        // add Portal
-
-        self.add_portal(ctx, 0.0, 0.0, 0.0);
-
-        // add stars
-        let num_big = self.rng.gen_range(1, 6);
-        let mut i = 0;
-        'star_loop_large: while i < num_big {
-            let a = self.rng.gen_range(0.0, std::f64::consts::PI * 2.0);
-            let d = self.rng.gen_range(450.0, 3000.0);
-
-            let x = d * a.cos();
-            let y = d * a.sin();
-            let s = self.rng.gen_range(300.0, 360.0 + (300.0 / (num_big as f64)));
-
-            for (cid, c) in &self.c_collider {
-                let colpos = &self.c_pos.get(cid).unwrap();
-                
-                let dx = colpos.x - x;
-                let dy = colpos.y - y;
-
-                let rdist = c.rad + s;
-                if (rdist * rdist) > ((dx * dx) + (dy * dy)) {
-                    // would overlap
-                    continue 'star_loop_large;
-                }
-            }
-
-            self.add_star(
-                ctx,
-                x, y,
-                s,
-                true,
-            );
-
-            i += 1;
-        }
-
-        let num_small = self.rng.gen_range(45, 180);
-        let mut i = 0;
-        'star_loop_small: while i < num_small {
-            let a = self.rng.gen_range(0.0, std::f64::consts::PI * 2.0);
-            let d = self.rng.gen_range(999.0, 6000.0);
-
-            let x = d * a.cos();
-            let y = d * a.sin();
-            let s = self.rng.gen_range(45.0, 120.0);
-
-            for (cid, c) in &self.c_collider {
-                let colpos = &self.c_pos.get(cid).unwrap();
-                
-                let dx = colpos.x - x;
-                let dy = colpos.y - y;
-                let rdist = c.rad + s;
-                if (rdist * rdist) > ((dx * dx) + (dy * dy)) {
-                    // would overlap
-                    continue 'star_loop_small;
-                }
-            }
-
-            self.add_star(
-                ctx,
-                x, y,
-                s,
-                false,
-            );
-
-            i += 1;
-        }
-
-        let (_, turret_r) = self.meshs[MeshNum::HashMesh as usize];
-        let turret_r = turret_r as f64;
-        let mut i = 0;
-        let turret_amt = 1 + (2 * level);
-        'turret_loop: while i < turret_amt {
-            let a = self.rng.gen_range(0.0, std::f64::consts::PI * 2.0);
-            let d = self.rng.gen_range(900.0, 6600.0);
-
-            let x = d * a.cos();
-            let y = d * a.sin();
-
-            let a = self.rng.gen_range(-std::f32::consts::PI, std::f32::consts::PI);
-
-            for (cid, c) in &self.c_collider {
-                let colpos = &self.c_pos.get(cid).unwrap();
-                
-                let dx = colpos.x - x;
-                let dy = colpos.y - y;
-                let rdist = c.rad + turret_r;
-                if (rdist * rdist) > ((dx * dx) + (dy * dy)) {
-                    // would overlap
-                    continue 'turret_loop;
-                }
-            }
-
-            self.add_turret(
-                ctx,
-                x, y, a
-            );
-
-            i += 1;
-        }
-
-        let (_, fuel_r) = self.meshs[MeshNum::HashMesh as usize];
-        let fuel_r = fuel_r as f64;
-        let mut i = 0;
-        let fuel_amt = 90 / (level + 1);
-        'fuel_loop: while i < fuel_amt {
-            let a = self.rng.gen_range(0.0, std::f64::consts::PI * 2.0);
-            let d = self.rng.gen_range(450.0, 4500.0);
-
-            let x = d * a.cos();
-            let y = d * a.sin();
-
-            for (cid, c) in &self.c_collider {
-                let colpos = &self.c_pos.get(cid).unwrap();
-                
-                let dx = colpos.x - x;
-                let dy = colpos.y - y;
-                let rdist = c.rad + fuel_r + 1.0;
-                if (rdist * rdist) > ((dx * dx) + (dy * dy)) {
-                    // would overlap
-                    continue 'fuel_loop;
-                }
-            }
-
-            self.add_fuel_powerup(
-                ctx,
-                x, y,
-            );
-
-            i += 1;
-        }
-
-        let (_, lock_r) = self.meshs[MeshNum::HashMesh as usize];
-        let lock_r = lock_r as f64;
-        let num_locks = level+1;
-        let mut i = 0;
-        'lock_loop: while i < num_locks {
-            let a = self.rng.gen_range(0.0, std::f64::consts::PI * 2.0);
-            let d = self.rng.gen_range(300.0, 6000.0);
-
-            let x = d * a.cos();
-            let y = d * a.sin();
-
-            for (cid, c) in &self.c_collider {
-                let colpos = &self.c_pos.get(cid).unwrap();
-                
-                let dx = colpos.x - x;
-                let dy = colpos.y - y;
-                let rdist = c.rad + lock_r + 45.0;
-                if (rdist * rdist) > ((dx * dx) + (dy * dy)) {
-                    // would overlap
-                    continue 'lock_loop;
-                }
-            }
-            self.add_lock(
-                ctx,
-                x, y, a as f32,
-            );
-
-            i += 1;
-        }
-
-        let mut spawned_player = false;
-        let (_, player_r) = self.meshs[MeshNum::AngMesh as usize];
-        let player_r = player_r as f64;
-        'player_loop: while !spawned_player {
-            let a = self.rng.gen_range(0.0, std::f64::consts::PI * 2.0);
-            let d = self.rng.gen_range(5500.0, 6900.0);
-
-            let x = d * a.cos();
-            let y = d * a.sin();
-
-            for (cid, c) in &self.c_collider {
-                let colpos = &self.c_pos.get(cid).unwrap();
-                
-                let dx = colpos.x - x;
-                let dy = colpos.y - y;
-                let rdist = c.rad + player_r + 100.0;
-                if (rdist * rdist) > ((dx * dx) + (dy * dy)) {
-                    // would overlap
-                    continue 'player_loop;
-                }
-            }
-
-            spawned_player = true;
-
-            let shipid = self.add_ship(
-                ctx,
-                MeshNum::AngMesh,
-                x, y,
-                PLAYER_THRUST, PLAYER_EMPTY_THRUST, PLAYER_FUEL,
-                PLAYER_AMMO,
-            );
-            self.make_player(shipid);
-
-            // add prediction on the player ship
-            self.add_prediction(ctx, shipid, true);
-        }
-        
-        self.started = true;
-    }
-
-    fn make_player(&mut self, id: IdVal) {
-        self.playerid = Some(id);
-    }
-
-    fn add_entity(&mut self) -> IdVal {
-        let id = self.next_id;
-        self.next_id += 1;
-
-        self.entities.push(
-            Entity{
-                id,
-                to_destroy: false
-            }
-        );
-
-        return id;
-    }
-
-    fn add_portal(&mut self, _ctx: &mut Context, x: f64, y: f64, a: f32) -> IdVal {
-        let id = self.add_entity();
-
-        let i = MeshNum::ClosedMesh as usize;
-        let (_, r) = self.meshs[i];
-
-        self.c_pos.insert(
-            id,
-            CPos{x, y, a},
-        );
-        self.c_grav.insert(
-            id,
-            CGrav{mass: PORTAL_GRAV, dist2: std::f64::INFINITY},
-        );
-        self.c_drawable.insert(
-            id,
-            CDrawable{
-                thing: DrawThing::MeshInd(i),
-                r,
-                minsz: 0.0,
-            }
-        );
-        self.c_collider.insert(
-            id,
-            CCollider{
-                rad: r as f64,
-                col_action: CollisionType::None,
-                stop_col: false,
-            },
-        );
-
-        self.portal = Some(id);
-
-        return id;
-    }
-
-    fn add_lock(&mut self, _ctx: &mut Context, x: f64, y: f64, a: f32) -> IdVal {
-        let id = self.add_entity();
-
-        let i = MeshNum::LockMesh as usize;
-        let (_, r) = self.meshs[i];
-
-        self.c_pos.insert(
-            id,
-            CPos{x, y, a},
-        );
-        self.c_drawable.insert(
-            id,
-            CDrawable{
-                thing: DrawThing::MeshInd(i),
-                r,
-                minsz: 0.0,
-            }
-        );
-        self.c_collides.insert(
-            id,
-            CCollides{
-                rad: r as f64,
-            },
-        );
-
-        self.locks.push(id);
-
-        return id;
-    }
-
-    fn add_turret(&mut self, _ctx: &mut Context, x: f64, y: f64, a: f32) -> IdVal {
-        let id = self.add_entity();
-
-        let i = MeshNum::HashMesh as usize;
-        let (_, r) = self.meshs[i];
-
-        self.c_drawable.insert(
-            id,
-            CDrawable{
-                thing: DrawThing::MeshInd(i),
-                r,
-                minsz: 0.0,
-            }
-        );
-        self.c_pos.insert(
-            id,
-            CPos{x, y, a},
-        );
-        self.c_collides.insert(
-            id,
-            CCollides{
-                rad: r as f64,
-            },
-        );
-
-        self.c_turret.insert(
-            id,
-            CTurret{
-                fire_rate: TURRET_FIRE_RATE,
-                till_next_shot: 0.0,
-            },
-        );
-
-        return id;
+    fn mouse_world(&self, ctx: &mut Context) -> (f64, f64) {
+        return self.cam.cam2world(&graphics::screen_coordinates(ctx), self.mx, self.my);
     }
 
-    fn add_fuel_powerup(&mut self, _ctx: &mut Context, x: f64, y: f64) -> IdVal {
-        let id = self.add_entity();
-
-        let i = MeshNum::AstMesh as usize;
-        let (_, r) = self.meshs[i];
-
-        self.c_drawable.insert(
-            id,
-            CDrawable{
-                thing: DrawThing::MeshInd(i),
-                r,
-                minsz: 0.0,
-            }
-        );
-        self.c_collider.insert(
-            id,
-            CCollider{
-                rad: r as f64,
-                col_action: CollisionType::FuelPup(FUEL_PER_PUP),
-                stop_col: false,
-            },
-        );
-        self.c_pos.insert(
-            id,
-            CPos{x, y, a: 0.0},
-        );
-        self.c_collides.insert(
-            id,
-            CCollides{
-                rad: r as f64,
-            },
-        );
-
-        return id;
-    }
-
-    fn add_prediction(&mut self, _ctx: &mut Context, objid: IdVal, drawable: bool) -> IdVal {
-        let p_id = self.add_entity();
-
-        let mut ptsvec = Vec::new();
-        //Add points
-        for _ in 0..PRED_AMT {
-            ptsvec.push([0.0,0.0]);
-        }
-        self.c_predictable.insert(
-            p_id,
-            CPredictable{
-                objid,
-                pts: ptsvec,
-                tstep: PRED_TSTEP,
-                rate: PRED_RATE,
-                till_next: 0.0,
-                valid_len: 0,
-                collidable: true,
-                color: PRED_COLOR,
-            },
-        );
-        if drawable {
-            self.c_drawable.insert(
-                p_id,
-                CDrawable{
-                    thing: DrawThing::Blank,
-                    r: std::f32::INFINITY,
-                    minsz: 0.0, // we do this in the mesh gen
-                },
-            );
-        }
-        self.c_pos.insert(
-            p_id,
-            CPos{x: 0.0, y: 0.0, a: 0.0},
-        );
-
-        return p_id;
-    }
-
-    fn add_star(&mut self, ctx: &mut Context, x: f64, y: f64, size: f64, always_pull: bool) -> IdVal {
-        let id = self.add_entity();
-
-        self.c_pos.insert(
-            id,
-            CPos{x, y, a: 0.0},
-        );
-        let mass = STAR_GRAV_MUL * size * size * size;
-        // GRAV_REACH = mass / dist2
-        //dist2 = mass / GRAV_REACH
-        let dist2 = if always_pull {
-            std::f64::INFINITY
-        } else {
-            mass / GRAV_REACH
-        };
-
-        self.c_grav.insert(
-            id,
-            CGrav{mass, dist2: dist2},
-        );
-        self.c_drawable.insert(
-            id,
-            CDrawable{
-                thing: DrawThing::Mesh(
-                    graphics::Mesh::new_circle(
-                        ctx,
-                        graphics::DrawMode::fill(),
-                        [0.0, 0.0],
-                        size as f32,
-                        STAR_RES,
-                        graphics::WHITE,
-                    ).unwrap()
-                ),
-                r: size as f32,
-                minsz: 0.0,
-            },
-        );
-        self.c_collider.insert(
-            id,
-            CCollider{
-                rad: size,
-                col_action: CollisionType::Explosion(STAR_E_SZ, false),
-                stop_col: true,
-            },
-        );
-
-        return id;
-    }
-
-    fn add_ship(&mut self, ctx: &mut Context, m: MeshNum, x: f64, y: f64, thrust: f64, empty_thrust: f64, fuel: f64, ammo: usize) -> IdVal {
-        let id = self.add_entity();
-
-        self.c_pos.insert(
-            id,
-            CPos{x, y, a: 0.0},
-        );
-        let i = m as usize;
-        let (_, rad) = self.meshs[i];
-        self.c_drawable.insert(
-            id,
-            CDrawable{
-                thing: DrawThing::MeshInd(i),
-                r: rad,
-                minsz: SHIP_MINSZ,
-            },
-        );
-        self.c_dynamic.insert(
-            id,
-            CDynamic {
-                x_vel: 0.0,
-                y_vel: 0.0,
-                in_ax: 0.0,
-                in_ay: 0.0,
-            },
-        );
-        self.c_collides.insert(
-            id,
-            CCollides{
-                rad: rad as f64,
-            },
-        );
-        self.c_ship.insert(
-            id,
-            CShip {
-                thrust,
-                empty_thrust,
-                fuel,
-                ammo,
-            }
-        );
-
-        self.add_trail(ctx, &id, SHIP_TRAIL_SZ, SHIP_TRAIL_COLOR);
-
-        return id;
-    }
-
-    fn add_trail(&mut self, _ctx: &mut Context, pid: &IdVal, size: f32, color: [f32; 4]) -> IdVal {
-        let id = self.add_entity();
-        self.c_trail.insert(
-            id,
-            CTrail{
-                objid: *pid,
-                pts: Vec::new(),
-                max_len: SHIP_TRAIL_AMT,
-                size,
-                color: color,
-                dist: TRAIL_DIST,
-            },
-        );
-        self.c_drawable.insert(
-            id,
-            CDrawable{
-                thing: DrawThing::Blank,
-                r: std::f32::INFINITY,
-                minsz: 0.0,
-            },
-        );
-        self.c_pos.insert(
-            id,
-            CPos{x: 0.0, y: 0.0, a: 0.0},
-        );
-
-        return id;
-    }
-
-    fn spawn_explosion(&mut self, ctx: &mut Context, px: f64, py: f64, size: f32, collidable: bool) -> IdVal {
-        let id = self.add_entity();
-        self.c_pos.insert(
-            id,
-            CPos{x: px, y: py, a: 0.0},
-        );
-
-        let s64 = size as f64;
-        let tg = E_TG * s64;
-        let ts = tg + (E_TS * s64);
-        let tf = ts + (E_TF * s64);
-        self.c_explosion.insert(
-            id,
-            CExplosion{
-                grow_size: size,
-                time_grow: tg,
-                time_stay: ts,
-                time_fade: tf,
-                time_so_far: 0.0,
-            },
-        );
-
-        self.c_drawable.insert(
-            id,
-            CDrawable{
-                thing: DrawThing::MeshScale(
-                    graphics::Mesh::new_circle(
-                        ctx,
-                        graphics::DrawMode::stroke(EXPLOSION_STROKE),
-                        [0.0, 0.0],
-                        size,
-                        EXPLOSION_RES,
-                        graphics::Color::from(EXPLOSION_COLOR),
-                    ).unwrap(),
-                    0.0,
-                ),
-                r: 30.0,
-                minsz: 0.0,
-            },
-        );
-
-        if collidable {
-            self.c_collider.insert(
-                id,
-                CCollider{
-                    rad: 0.0,
-                    col_action: CollisionType::Explosion(size/1.5, false),
-                    stop_col: false,
-                },
-            );
+    // the editor works on the zone as it is now, and leaving it plays what was made
+    // not while recording or replaying, the replay only knows the zone it started with
+    // or after a game over, there is no ship left to play it with
+    fn toggle_editor(&mut self) {
+        if self.world.game_over {
+            return;
         }
-
-        return id;
-    }
-
-    fn spawn_nuke(&mut self, ctx: &mut Context, px: f64, py: f64, a: f32, vx: f64, vy: f64, thrust: f64, target: Option<IdVal>, explosion_size: f32) {
-        let id = self.add_entity();
-
-        self.c_pos.insert(
-            id,
-            CPos{x: px, y: py, a: a},
-        );
-
-        let i = MeshNum::NukeMesh as usize;
-        let (_, rad) = self.meshs[i];
-        self.c_drawable.insert(
-            id,
-            CDrawable{
-                thing: DrawThing::MeshInd(i),
-                r: rad,
-                minsz: 0.0,
-            },
-        );
-        self.c_dynamic.insert(
-            id,
-            CDynamic {
-                x_vel: vx,
-                y_vel: vy,
-                in_ax: 0.0,
-                in_ay: 0.0,
+        match self.editor.take() {
+            Some(e) => {
+                let level = self.world.level;
+                self.world.load_level(&e.level, level);
             },
-        );
-        self.c_collides.insert(
-            id,
-            CCollides{
-                rad: rad as f64,
-            },
-        );
-        if thrust != 0.0 {
-            self.c_rocket.insert(
-                id,
-                CRocket{
-                    thrust,
-                    target,
+            None => {
+                if !self.world.started || self.recording.is_some() || self.replaying.is_some() {
+                    return;
                 }
-            );
-        }
-
-        self.add_trail(ctx, &id, NUKE_TRAIL_SZ, NUKE_TRAIL_COLOR);
-
-        self.c_collider.insert(
-            id,
-            CCollider{
-                rad: rad as f64,
-                col_action: CollisionType::Explosion(explosion_size, true),
-                stop_col: false,
+                self.editor = Some(Editor::new(LevelFile::from_world(&self.world)));
             },
-        );
-    }
-
-    fn get_grav_a(gravs: &HashMap<IdVal, CGrav>, pos: &HashMap<IdVal, CPos>, px: f64, py: f64, id: &IdVal) -> (f64, f64, usize) {
-        let mut ax: f64 = 0.0;
-        let mut ay: f64 = 0.0;
-
-        let mut count = 0;
-
-        for (gid, g) in gravs {
-            if *gid == *id {
-                continue;
-            }
-
-            let gp = &pos[gid];
-
-            let dx = gp.x - px;
-            let dy = gp.y - py;
-
-            if dx == 0.0 && dy == 0.0 {
-                continue;
-            }
-
-            let r2 = (dx * dx) + (dy * dy);
-            if r2 > g.dist2 {
-                continue;
-            }
-
-            count += 1;
-
-            let r = r2.sqrt();
-            let r3 = r2 * r;
-
-            // get accelaration due to this item
-            let ga = g.mass / r3;
-            ax += ga * dx;
-            ay += ga * dy;
-        }
-
-        return (ax, ay, count);
-    }
-
-    fn raycast(colliders: &HashMap<IdVal, CCollider>, pos: &HashMap<IdVal, CPos>, px1: f64, py1: f64, px2: f64, py2: f64) -> bool {
-        // dist from line to point
-        // (a * x0 + b * y0 + c) / sqrt((a*a) + (b*b))
-        // where a = (y1-y2), b = (x2-x1), c = ((x1-x2)y1 + (y2-y1)x1)
-        // point 0 being the circle, points 1 and 2 being the ends of the line
-
-        let b = px2 - px1;
-        let a = py1 - py2;
-        let c = ((px1 - px2) * py1) + ((py2 - py1) * px1);
-        let d = (b * b) + (a * a);
-
-        for (cid, col) in colliders {
-            // check if line in circles
-            let cp = pos.get(cid).unwrap();
-            let mut dist2 = (a * cp.x) + (b * cp.y) + c;
-            dist2 *= dist2;
-            dist2 /= d;
-
-            if dist2 < (col.rad * col.rad) {
-                return false;
-            }
-        }
-
-        return true;
-    }
-
-    fn s_turret(&mut self, ctx: &mut Context, mut dt: f64) {
-        self.s_turret_next += dt;
-        if self.s_turret_next > TURRET_UPDATE_RATE {
-            dt = self.s_turret_next;
-            self.s_turret_next = 0.0;
-        } else {
-            return;
-        }
-
-        if self.playerid.is_none() {
-            return;
-        }
-
-        let pid = self.playerid.unwrap();
-        let ppos = self.c_pos.get(&pid).unwrap();
-
-        let mut launch_nuke = false;
-        let mut na = 0.0;
-        let mut nxv = 0.0;
-        let mut nyv = 0.0;
-        let mut npx = 0.0;
-        let mut npy = 0.0;
-
-        for (id, t) in &mut self.c_turret {
-            t.till_next_shot -= dt;
-            if t.till_next_shot > 0.0 {
-                continue;
-            }
-            let p = self.c_pos.get(id).unwrap();
-            let dx = p.x - ppos.x;
-            let dy = p.y - ppos.y;
-            let d2 = (dx*dx)+(dy*dy);
-            if d2 < TURRET_DIST2 && State::raycast(
-                &self.c_collider, &self.c_pos,
-                ppos.x, ppos.y,
-                p.x, p.y,
-            ) {
-                t.till_next_shot = t.fire_rate;
-                // spawn nuke
-                launch_nuke = true;
-                na = dy.atan2(dx) as f32;
-                let nac = -na.cos() as f64;
-                let nas = -na.sin() as f64;
-                nxv = nac * TURRET_NUKE_IVEL;
-                nyv = nas * TURRET_NUKE_IVEL;
-                npx = p.x + (nac * TURRET_NUKE_DIST);
-                npy = p.y + (nas * TURRET_NUKE_DIST);
-
-                break; // don't have to all fire at once
-            }
-        }
-
-        if launch_nuke {
-            self.spawn_nuke(
-                ctx,
-                npx, npy, // pos
-                na, // angle
-                nxv, // xvel
-                nyv, // yvel
-                TURRET_NUKE_THRUST, // thrust
-                Some(pid), // target
-                TURRET_NUKE_SIZE, // explosion size
-            );
-        }
-    }
-
-    fn s_explosion(&mut self, _ctx: &mut Context, dt: f64) {
-        for (id, ex) in &mut self.c_explosion {
-            ex.time_so_far += dt;
-            let d = &mut self.c_drawable.get_mut(id).unwrap();
-
-            if ex.time_so_far <= ex.time_grow {
-                // grow
-                let mut r = ex.time_so_far / ex.time_grow;
-                r = r.sqrt();
-                if let DrawThing::MeshScale(_, ref mut sc) = d.thing {
-                    *sc = r as f32;
-                }
-                r *= ex.grow_size as f64;
-                d.r = r as f32;
-                if let Some(c) = &mut self.c_collider.get_mut(id) {
-                    c.rad = r;
-                }
-            } else if ex.time_so_far <= ex.time_stay {
-                continue;
-            } else if ex.time_so_far <= ex.time_fade{
-                // fade out?
-                let mut r = 1.0 - ((ex.time_so_far - ex.time_stay) / (ex.time_fade - ex.time_stay));
-                if let DrawThing::MeshScale(_, ref mut sc) = d.thing {
-                    *sc = r as f32;
-                }
-                r *= ex.grow_size as f64;
-                d.r = r as f32;
-                if let Some(c) = &mut self.c_collider.get_mut(id) {
-                    c.rad = r;
-                }
-            } else {
-                // remove this explosion
-                for e in &mut self.entities {
-                    if e.id == *id {
-                        e.to_destroy = true;
-                        break;
-                    }
-                }
-            }
-        }
-    }
-
-    fn s_rocket(&mut self, _ctx: &mut Context, _dt: f64) {
-        for (id, r) in &mut self.c_rocket {
-            // if they have a target, orient them
-            let pa;
-            if let Some(tid) = &r.target {
-                let tpx;
-                let tpy;
-                if let Some(tp) = self.c_pos.get(tid) {
-                    tpx = tp.x;
-                    tpy = tp.y;
-                } else {
-                    // target it probably destroyed
-                    r.target = None;
-                    continue;
-                }
-                let p = self.c_pos.get_mut(id).unwrap();
-                let dx = p.x - tpx;
-                let dy = p.y - tpy;
-                p.a = dy.atan2(dx) as f32;
-                pa = p.a;
-            } else {
-                let p = self.c_pos.get_mut(id).unwrap();
-                pa = p.a;
-            }
-            // do thrust
-            let d = self.c_dynamic.get_mut(id).unwrap();
-
-            d.in_ax = -pa.cos() as f64 * r.thrust;
-            d.in_ay = -pa.sin() as f64 * r.thrust;
-
-        }
-    }
-
-    fn s_trail(&mut self, ctx: &mut Context, _dt: f64) {
-        for (id, t) in &mut self.c_trail {
-            match self.c_pos.get(&t.objid) {
-                Some(p) => {
-                    let px = p.x as f32;
-                    let py = p.y as f32;
-                    if t.pts.len() < 3 || (t.pts[1][0] - px).abs() > t.dist  || (t.pts[1][1] - py).abs() > t.dist {
-                        t.pts.insert(0, [px, py]);
-                        if t.pts.len() > t.max_len {
-                            t.pts.pop();
-                        }
-                    } else {
-                        t.pts[0][0] = px;
-                        t.pts[0][1] = py;
-                    }
-
-                    if let Some(mut d) = self.c_drawable.get_mut(&id) {
-                        d.thing = gen_fading_path(ctx, &t.pts[..], t.size, t.color);
-                    }
-                },
-                None => {
-                    // object must have been deleted
-                    // we should go too
-                    for e in &mut self.entities {
-                        if e.id == *id {
-                            e.to_destroy = true;
-                            break;
-                        }
-                    }
-                }
-            }
         }
+        self.acc = 0.0;
+        self.world.input.lmb = false;
+        self.world.input.rmb = false;
     }
 
-    fn s_predict(&mut self, ctx: &mut Context, dt: f64) {
-        // for each dyn object for each gravity object in range
-        for (id, p) in &mut self.c_predictable {
-            // different rates for different items
-            p.till_next -= dt;
-            if p.till_next > 0.0 {
-                continue;
-            }
-            p.till_next = p.rate;
-
-            match self.c_dynamic.get(&p.objid) {
-                Some(obj) => {
-                    let objp = self.c_pos.get(&p.objid).expect("Predictables.objid must have pos");
-
-                    let mut fx = objp.x;
-                    let mut fy = objp.y;
-                    let mut fvx = obj.x_vel;
-                    let mut fvy = obj.y_vel;
-
-                    p.valid_len = 0;
-                    let mut hit_something = false;
-                    for pt in &mut p.pts {
-                        p.valid_len += 1;
-                        *pt = [fx as f32, fy as f32];
-
-                        //fill out the points
-                        if hit_something {
-                            break;
-                        }
-
-                        let tmpid = 0;
-                        let (ax, ay, _) = State::get_grav_a(
-                            &self.c_grav,
-                            &self.c_pos,
-                            fx,
-                            fy,
-                            &tmpid,
-                        );
-
-                        // apply the accel to the velocity
-                        fvx += ax * p.tstep;
-                        fvy += ay * p.tstep;
-
-                        if p.valid_len < 2 && (fvx == 0.0 || fvy == 0.0) {
-                            break;
-                        }
-
-                        // apply the velocity to the position
-                        fx += fvx * p.tstep;
-                        fy += fvy * p.tstep;
-
-                        if p.collidable {
-                            let cobj = self.c_collides.get(&p.objid).unwrap();
-                            // check for collision
-                            for (cid, col) in &self.c_collider {
-                                if !col.stop_col {
-                                    continue
-                                }
-                                let cpos = &self.c_pos.get(cid).unwrap();
-                                let dcx = fx - cpos.x;
-                                let dcy = fy - cpos.y;
-                                let rdist = col.rad + cobj.rad;
-                                if (rdist * rdist) >= (dcx * dcx) + (dcy * dcy) {
-                                    hit_something = true;
-                                }
-                            }
-                        }
-                    }
-
-                    // if we have an assoicated CDrawable, update the mesh based on the points
-                    if let Some(mut d) = self.c_drawable.get_mut(&id) {
-                        d.thing = gen_fading_path(ctx, &p.pts[..p.valid_len], PRED_SIZE/self.cam.s, p.color);
-                    }
-                },
-                None => {
-                    // item must have been destroyed, and we should be too
-                    for e in &mut self.entities {
-                        if e.id == *id {
-                            e.to_destroy = true;
-                            break;
-                        }
-                    }
-                },
-            }
-        }
-    }
-
-    fn s_move(&mut self, _ctx: &mut Context, dt: f64) {
-        
-        // for each dyn object for each gravity object in range
-        for (id, d) in &mut self.c_dynamic {
-            let p = &self.c_pos[id];
-
-            let (mut ax, mut ay, _) = State::get_grav_a(&self.c_grav, &self.c_pos, p.x, p.y, id);
-            //self.grav_count = count;
-            ax += d.in_ax;
-            ay += d.in_ay;
-
-            // apply the accel to the velocity
-            d.x_vel += ax * dt;
-            d.y_vel += ay * dt;
-
-            // apply the velocity to the position
-            let p = self.c_pos.get_mut(id).unwrap();
-
-            p.x += d.x_vel * dt;
-            p.y += d.y_vel * dt;
-
-            // do rotational vel to the rotation as well
-            //TODO
+    // save the zone as it is now to the user data dir, to edit and play with --level
+    fn export_level(&self, ctx: &mut Context) {
+        if !self.world.started {
+            return;
+        }
+        let name = format!("/level_{}_{}.toml", self.world.seed, self.world.level);
+        let text = match self.editor {
+            Some(ref e) => e.level.to_string(),
+            None => LevelFile::from_world(&self.world).to_string(),
+        };
+        match write_user_file(ctx, &name, &text) {
+            Ok(()) => println!("Saved level to {}", filesystem::user_data_dir(ctx).join(&name[1..]).display()),
+            Err(e) => println!("Unable to save level {}: {}", name, e),
         }
     }
 
-    fn s_collision(&mut self, ctx: &mut Context, _dt: f64) {
-        let mut qe: Vec<(f64, f64, f32)> = Vec::new();
-        for (id, cobj) in &self.c_collides {
-            // check position against colliders
-            //TODO have different rates at which things check for collision?
+    // put the runs the world has finished with in the table and save it, replays were already played
+    // a run that ended can't be resumed any more, so the save goes as well
+    fn take_runs(&mut self, ctx: &mut Context) {
+        if self.world.ended_runs.is_empty() {
+            return;
+        }
+        let runs: Vec<_> = self.world.ended_runs.drain(..).collect();
+        if self.replaying.is_some() {
+            return;
+        }
 
-            let p = &self.c_pos[id];
-            for (cid, c) in &self.c_collider {
-                if *cid == *id {
-                    continue;
-                }
-                let colpos = &self.c_pos.get(cid).unwrap();
-                
-                let dx = colpos.x - p.x;
-                let dy = colpos.y - p.y;
-                let rdist = c.rad + cobj.rad;
-
-                if (rdist * rdist) >= ((dx * dx) + (dy * dy)) {
-                    // collided
-                    match c.col_action {
-                        CollisionType::Explosion(sz, delself) => {
-                            // queue spawn explosion
-                            let mut otherdie = false;
-                            // if the other collides had a collider
-                            // check if it wants an explosion as well
-                            if let Some(other_col) = self.c_collider.get(id) {
-                                if let CollisionType::Explosion(othersz, ds) = other_col.col_action {
-                                    if ds { // if the otherone wanted to go out on a hit, it wants to explode
-                                        qe.push((p.x, p.y, othersz));
-                                        otherdie = true;
-                                    }
-                                }
-                            }
-
-                            if !otherdie || delself {
-                                if delself {
-                                    qe.push((colpos.x, colpos.y, sz));
-                                } else {
-                                    qe.push((p.x, p.y, sz));
-                                }
-                            }
-                            
-                            for e in &mut self.entities {
-                                if delself && e.id == *cid {
-                                    e.to_destroy = true;
-                                }
-                                if e.id == *id {
-                                    e.to_destroy = true;
-                                    break;
-                                }
-                            }
-                        },
-                        CollisionType::FuelPup(amt) => {
-                            if let Some(ship) = &mut self.c_ship.get_mut(id) {
-                                ship.fuel += amt;
-                                for e in &mut self.entities {
-                                    if e.id == *cid {
-                                        e.to_destroy = true;
-                                        break;
-                                    }
-                                }
-                            }
-                        },
-                        CollisionType::Portal => {
-                            if let Some(_) = &self.c_ship.get_mut(id) {
-                                self.finished = true;
-                            }
-                        },
-                        CollisionType::None => (),
-                    }
+        // the saved run is over too, don't let it be carried on with next time
+        if filesystem::exists(ctx, SAVE_FILE) {
+            if let Err(e) = filesystem::delete(ctx, SAVE_FILE) {
+                println!("Unable to remove the saved run: {:?}", e);
+            }
+        }
 
-                    break;
-                }
+        let secs = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).map_or(0, |d| d.as_secs());
+        self.new_high = None;
+        for r in runs {
+            if r.points == 0 {
+                continue;
             }
+            self.new_high = self.high_scores.add(HighScore{
+                points: r.points,
+                zone: r.zone,
+                preset: r.preset,
+                seed: r.seed,
+                date: date(secs),
+            });
         }
-        for (px, py, sz) in qe {
-            self.spawn_explosion(ctx, px, py, sz, true);
+
+        let text = self.high_scores.to_string();
+        if let Err(e) = write_user_file(ctx, HIGH_SCORE_FILE, &text) {
+            println!("Unable to save high scores: {}", e);
         }
     }
 
-    fn s_player(&mut self, ctx: &mut Context, dt: f64) {
-        // apply inputs
-        // rotate to follow mouse
-        let mut launch_nuke = false;
-        let mut npx = 0.0;
-        let mut npy = 0.0;
-        let mut na = 0.0;
-        let mut nxv = 0.0;
-        let mut nyv = 0.0;
-
-        if let Some(ref pid) = self.playerid {
-            let px;
-            let py;
-            let pa;
-            let (mx, my) = self.cam.cam2world(&graphics::screen_coordinates(ctx), self.input.mx, self.input.my);
-            {
-                let p = self.c_pos.get_mut(pid).unwrap();
-                px = p.x;
-                py = p.y;
-
-                // TODO use angular accelaration to rotate, don't just snap to mouse
-                // tan = o/a
-                let dx = px - mx;
-                let dy = py - my;
-                p.a = dy.atan2(dx) as f32;
-                pa = p.a;
-            }
+    // write the run as it is now to the user data dir, true if it was
+    // not while replaying since the replay has the run already, or editing since the zone is in bits
+    fn save_run(&self, ctx: &mut Context) -> bool {
+        if !self.world.started || self.replaying.is_some() || self.editor.is_some() {
+            return false;
+        }
+        let mut s = Save::from_world(&self.world);
+        s.cam = Some((self.cam.x, self.cam.y, self.cam.s));
+        let text = s.to_string();
+        match write_user_file(ctx, SAVE_FILE, &text) {
+            Ok(()) => {
+                println!("Saved run to {}", filesystem::user_data_dir(ctx).join(&SAVE_FILE[1..]).display());
+                return true;
+            },
+            Err(e) => {
+                println!("Unable to save run: {}", e);
+                return false;
+            },
+        }
+    }
 
+    // carry on from the saved run, not while recording or replaying since those start from the beginning
+    fn resume_run(&mut self, ctx: &mut Context) {
+        if self.recording.is_some() || self.replaying.is_some() || self.editor.is_some() {
+            return;
+        }
+        if !filesystem::exists(ctx, SAVE_FILE) {
+            return;
+        }
+        let s = match read_user_file(ctx, SAVE_FILE).and_then(|text| Save::parse(&text)) {
+            Ok(s) => s,
+            Err(e) => {
+                println!("Unable to resume the saved run: {}", e);
+                return;
+            },
+        };
 
-            // accel based on mouse
-            let d = self.c_dynamic.get_mut(pid).unwrap();
-            let s = self.c_ship.get_mut(pid).unwrap();
+        if s.data != self.world.data_hash {
+            println!("The saved run was made with other tuning, difficulty or prefab files, it may not play the same");
+        }
+        let cam = s.cam;
+        if let Err(e) = s.restore(&mut self.world) {
+            println!("Unable to resume the saved run: {}", e);
+            return;
+        }
+        if let Some((x, y, sc)) = cam {
+            self.cam.x = x;
+            self.cam.y = y;
+            self.cam.s = sc;
+        }
+        self.cam.update = true;
+        self.acc = 0.0;
+        self.new_high = None;
+        self.world.input.lmb = false;
+        self.world.input.rmb = false;
+        println!("Resumed the saved run, zone {}", self.world.level);
+    }
 
-            d.in_ax = 0.0;
-            d.in_ay = 0.0;
+    fn high_score_text(&self) -> String {
+        let mut s = String::from("  high scores\n");
+        for (i, e) in self.high_scores.entries.iter().enumerate() {
+            s.push_str(&format!(
+                "{} {:2}. {:06}  zone {:02}  {:6}  {}  seed {}\n",
+                if self.new_high == Some(i) { ">" } else { " " },
+                i + 1,
+                e.points,
+                e.zone,
+                e.preset.name(),
+                e.date,
+                e.seed,
+            ));
+        }
+        return s;
+    }
 
-            let tamt = if s.fuel > 0.0 {
-                s.thrust
-            } else {
-                s.empty_thrust
+    // make sure every star and explosion has a circle mesh, and drop the ones no longer needed
+    fn update_circles(&mut self, ctx: &mut Context) {
+        let drawables = &self.world.c_drawable;
+        self.circles.retain(|id, _| drawables.contains_key(id));
+
+        let palette = &self.world.palette;
+        for (id, d) in drawables {
+            let (r, ring, mode, res, color) = match d.thing {
+                DrawThing::Circle(r) => {
+                    // stars that always pull have no cut off
+                    let big = self.world.c_grav.get(id).map_or(false, |g| !g.dist2.is_finite());
+                    (r, false, graphics::DrawMode::fill(), STAR_RES, if big { palette.big_star } else { palette.star })
+                },
+                DrawThing::Ring(r, _) => (r, true, graphics::DrawMode::stroke(EXPLOSION_STROKE), EXPLOSION_RES, EXPLOSION_COLOR),
+                _ => continue,
             };
-            //TODO taper thrust by mouse position
-            if self.input.rmb {
-                d.in_ax = -pa.cos() as f64 * tamt;
-                d.in_ay = -pa.sin() as f64 * tamt;
-            }
 
-            s.fuel -= (d.in_ax + d.in_ay).abs() * dt;
-            if s.fuel < 0.0 {
-                s.fuel = 0.0;
+            // the editor resizes stars and each kind of zone has its own colours, so check it is still the same circle
+            if let Some((cr, cring, ccolor, _)) = self.circles.get(id) {
+                if *cr == r && *cring == ring && *ccolor == color {
+                    continue;
+                }
             }
 
-            if self.input.lmb  && s.ammo > 0 {
-                let nd = PLAYER_NUKE_DIST;
-                let pa_x = -pa.cos() as f64;
-                let pa_y = -pa.sin() as f64;
-                npx = px + (pa_x * nd);
-                npy = py + (pa_y * nd);
-                na = pa;
-                nxv = d.x_vel + (pa_x * PLAYER_NUKE_IVEL);
-                nyv = d.y_vel + (pa_y * PLAYER_NUKE_IVEL);
-                launch_nuke = true;
-                
-                self.input.lmb = false;
-                s.ammo -= 1;
-            }
+            let m = graphics::Mesh::new_circle(
+                ctx,
+                mode,
+                [0.0, 0.0],
+                r,
+                res,
+                graphics::Color::from(color),
+            ).unwrap();
+            self.circles.insert(*id, (r, ring, color, m));
         }
+    }
 
-        if launch_nuke {
-            self.spawn_nuke(
-                ctx,
-                npx,
-                npy,
-                na,
-                nxv,
-                nyv,
-                PLAYER_NUKE_THRUST, // thrust
-                None, // target
-                PLAYER_NUKE_SIZE, // explosion size
-            );
+    fn draw_thing(&self, ctx: &mut Context, id: &IdVal, d: &CDrawable, mut param: graphics::DrawParam) -> error::GameResult {
+        if d.minsz != 0.0 && self.cam.s < d.minsz {
+            let s = d.minsz / self.cam.s;
+            param = param.scale([s, s]);
+        }
+        match d.thing {
+            DrawThing::Blank => return Ok(()),
+            DrawThing::Circle(_) => {
+                let (_, _, _, m) = &self.circles[id];
+                return graphics::draw(ctx, m, param);
+            },
+            DrawThing::Ring(_, sc) => {
+                let (_, _, _, m) = &self.circles[id];
+                return graphics::draw(ctx, m, param.scale([sc,sc]));
+            },
+            DrawThing::Path => {
+                let m = if let Some(t) = self.world.c_trail.get(id) {
+                    gen_fading_path(ctx, &t.pts[..], t.size, t.color)
+                } else if let Some(p) = self.world.c_predictable.get(id) {
+                    gen_fading_path(ctx, &p.pts[..p.valid_len], PRED_SIZE/self.cam.s, p.color)
+                } else {
+                    None
+                };
+                if let Some(m) = m {
+                    return graphics::draw(ctx, &m, param);
+                }
+                return Ok(());
+            },
+            DrawThing::MeshInd(i) => {
+                return graphics::draw(ctx, &self.meshs[i], param);
+            },
         }
     }
 
-    fn s_player_cam(&mut self) {
-        if let Some(ref pid) = self.playerid {
-            let p = &self.c_pos.get(pid).unwrap();
+    fn s_player_cam(&mut self, dt: f64) {
+        if let Some(ref pid) = self.world.playerid {
+            let (px, py, _) = self.world.lerp_pos(pid, self.acc / SIM_DT);
 
-            if self.cam.x != p.x || self.cam.y != p.y {
+            if self.cam.x != px || self.cam.y != py {
                 self.cam.update = true;
-                self.cam.x = p.x;
-                self.cam.y = p.y;
+                self.cam.x = px;
+                self.cam.y = py;
             }
+        } else if let Some(ref d) = self.world.death {
+            // ease over to the explosion
+            let k = (DEATH_CAM_RATE * dt).min(1.0);
+            self.cam.x += (d.x - self.cam.x) * k;
+            self.cam.y += (d.y - self.cam.y) * k;
+            self.cam.s += (DEATH_CAM_SCALE - self.cam.s) * (k as f32);
+            self.cam.update = true;
         }
     }
 }
 
 impl ggez::event::EventHandler for State {
     fn update(&mut self, ctx: &mut Context) -> GameResult<()> {
-        if self.finished {
-            self.gen_level(ctx, self.level+1);
-        } else if self.input.reset {
-            self.gen_level(ctx, 0);
-            self.input.reset = false;
-        }
-
         let dt = timer::duration_to_f64(timer::delta(ctx));
-        
+
         //if self.log_time <= timer::ticks(ctx) {
         //    self.log_time = timer::ticks(ctx) + LOG_TICKS;
         //    println!("fps: {}", timer::fps(ctx));
         //    println!("scale: {}", self.cam.s);
-        //    println!("grav_count: {}", self.grav_count);
         //    println!(" - ");
         //}
 
-        self.s_player(ctx, dt);
-        self.s_move(ctx, dt);
-        self.s_collision(ctx, dt);
-        self.s_predict(ctx, dt);
-        self.s_trail(ctx, dt);
-        self.s_turret(ctx, dt);
-        self.s_rocket(ctx, dt);
-        self.s_explosion(ctx, dt);
+        // pick up edits to the tuning and prefabs, but not while recording or replaying
+        // since the replay doesn't know about them
+        if self.recording.is_none() && self.replaying.is_none() {
+            self.data.next_check -= dt;
+            if self.data.next_check <= 0.0 {
+                self.data.next_check = DATA_CHECK_RATE;
+                let newest = self.data.newest();
+                if newest != self.data.stamp {
+                    self.data.stamp = newest;
+                    match self.data.load(&mut self.world) {
+                        Ok(()) => println!("Reloaded tuning and prefabs"),
+                        Err(e) => println!("Keeping the old tuning and prefabs, {}", e),
+                    }
+                }
+            }
+        }
+
+        if self.editor.is_some() {
+            // paused, just move the camera around and keep the world up to date with the layout
+            let i = &self.world.input;
+            let mv = EDITOR_PAN * dt / (self.cam.s as f64);
+            let dx = (i.right as i32 - i.left as i32) as f64;
+            let dy = (i.down as i32 - i.up as i32) as f64;
+            if dx != 0.0 || dy != 0.0 {
+                self.cam.x += dx * mv;
+                self.cam.y += dy * mv;
+                self.cam.update = true;
+            }
+
+            let (mx, my) = self.mouse_world(ctx);
+            let e = self.editor.as_mut().unwrap();
+            e.drag(mx, my);
+            e.rebuild(&mut self.world);
+            return Ok(());
+        }
+
+        // step in fixed amounts, and after a hitch give up on catching up rather than spiral
+        self.acc += dt.min(SIM_DT * (MAX_STEPS as f64));
+
+        // the world only knows about the mouse in world space
+        let (mx, my) = self.cam.cam2world(&graphics::screen_coordinates(ctx), self.mx, self.my);
+
+        while self.acc >= SIM_DT {
+            self.acc -= SIM_DT;
+
+            if let Some(ref mut r) = self.replaying {
+                // inputs come from the replay, ignore the live ones
+                match r.play(&mut self.world) {
+                    Some(rdt) => self.world.step(rdt),
+                    None => (),
+                }
+                continue;
+            }
+
+            self.world.input.mx = mx;
+            self.world.input.my = my;
+
+            if let Some(ref mut r) = self.recording {
+                r.record(&self.world, SIM_DT);
+            }
 
-        self.s_destroy();
+            self.world.step(SIM_DT);
+        }
+        self.take_runs(ctx);
 
         return Ok(());
     }
@@ -1695,13 +579,22 @@ impl ggez::event::EventHandler for State {
         let sc = graphics::screen_coordinates(ctx);
         let dp = graphics::DrawParam::default();
 
-        graphics::clear(ctx, graphics::BLACK);
-        
-        self.s_player_cam();
+        graphics::clear(ctx, graphics::Color::from(self.world.palette.background));
+
+        if self.editor.is_none() {
+            self.s_player_cam(timer::duration_to_f64(timer::delta(ctx)));
+        }
         self.cam.do_update(ctx, &sc);
 
-        if !self.started {
-            let mut ui = graphics::Text::new(GUIDE);
+        if !self.world.started {
+            let mut ui = graphics::Text::new(
+                format!(
+                    "{}\n    1 / 2 / 3 for Easy / Normal / Hard: {}\n\n{}",
+                    GUIDE,
+                    self.world.preset.name(),
+                    if self.high_scores.entries.is_empty() { String::new() } else { self.high_score_text() },
+                ),
+            );
 
             ui.set_font(self.font, graphics::Scale{x: 24.0, y: 24.0});
 
@@ -1720,49 +613,141 @@ impl ggez::event::EventHandler for State {
             return Ok(())
         }
 
-        for (id, d) in &self.c_drawable {
-            let p = &self.c_pos.get(id).expect("Drawables must have a position");
+        self.update_circles(ctx);
+
+        // draw between the last two steps by how far we are into the next one
+        let alpha = self.acc / SIM_DT;
+
+        for (id, d) in &self.world.c_drawable {
+            let (px, py, pa) = self.world.lerp_pos(id, alpha);
             let mut objr = d.r;
-            if let DrawThing::MeshScale(_, ms) = d.thing {
+            if let DrawThing::Ring(_, ms) = d.thing {
                 objr *= ms;
             }
 
             //don't draw objects off screen
-            if !self.cam.is_visible(ctx, &sc, p.x, p.y, objr) {
+            if !self.cam.is_visible(ctx, &sc, px, py, objr) {
                 continue;
             }
-            let item_dp = dp.dest([p.x as f32, p.y as f32]).rotation(p.a);
-            d.draw(
-                self,
+            let item_dp = dp.dest([px as f32, py as f32]).rotation(pa);
+            self.draw_thing(
                 ctx,
+                id,
+                d,
                 item_dp,
             )?;
         }
 
+        // beams go from the turret to wherever they stop
+        for (id, t) in &self.world.c_turret {
+            if let Some((ex, ey)) = t.beam_to {
+                let (px, py, _) = self.world.lerp_pos(id, alpha);
+                if ((ex - px).powi(2) + (ey - py).powi(2)) < 1.0 {
+                    continue;
+                }
+                let m = graphics::Mesh::new_line(
+                    ctx,
+                    &[[px as f32, py as f32], [ex as f32, ey as f32]],
+                    (BEAM_SIZE / self.cam.s).max(BEAM_MIN),
+                    graphics::Color::from(BEAM_COLOR),
+                )?;
+                graphics::draw(ctx, &m, dp)?;
+            }
+        }
+
         // draw ui
-        if let Some(pid) = self.playerid {
-            //let p = self.c_pos.get(&pid).unwrap();
-            let s = self.c_ship.get(&pid).unwrap();
-            let d = self.c_dynamic.get(&pid).unwrap();
+        if let Some(pid) = self.world.playerid {
+            //let p = self.world.c_pos.get(&pid).unwrap();
+            let s = self.world.c_ship.get(&pid).unwrap();
+            let d = self.world.c_dynamic.get(&pid).unwrap();
             let mut ui = graphics::Text::new(
                 format!(
                     concat!(
                         "/-----------------\\\n",
                         "|   fuel : {:04.0}   |\n",
+                        "|   hull : {:03.0}    |\n",
                         "|   zone : {:02}     |\n",
                         "|    vel : {:04.0}   |\n",
                         "|  nukes : {:02}     |\n",
                         "|  locks : {:02}     |\n",
-                        "\\-----------------/\n", 
+                        "|  ships : {}     |\n",
+                        "|  score : {:06} |\n",
+                        "\\-----------------/\n",
+                        "seed {}\n",
+                        "{}",
+                        "{}",
                     ),
                     s.fuel,
-                    self.level,
+                    s.hull.max(0.0),
+                    self.world.level,
                     ((d.x_vel * d.x_vel) + (d.y_vel * d.y_vel)).sqrt(),
                     s.ammo,
-                    self.locks.len(),
+                    self.world.locks.len(),
+                    self.world.lives.map_or("--".to_string(), |l| format!("{:02}", l)),
+                    self.world.score.points,
+                    self.world.seed,
+                    if self.world.theme.is_empty() { String::new() } else { format!("{} zone\n", self.world.theme) },
+                    // each capital ship still out there
+                    self.world.c_pilot.iter()
+                        .filter(|(_, pl)| pl.boss)
+                        .filter_map(|(id, _)| self.world.c_ship.get(id))
+                        .map(|s| format!("capital hull {:04.0}\n", s.hull.max(0.0)))
+                        .collect::<String>(),
+                ),
+            );
+
+            ui.set_font(self.font, graphics::Scale{x: 18.0, y: 18.0});
+
+            let (uix, uiy) = self.cam.cam2world(&sc, 15.0 * self.cam.s, 15.0 * self.cam.s);
+            graphics::draw(
+                ctx,
+                &ui,
+                dp.dest([uix as f32, uiy as f32]).scale([1.0 / self.cam.s, 1.0 / self.cam.s]),
+            ).unwrap();
+        }
+
+        if self.world.game_over {
+            let mut ui = graphics::Text::new(
+                format!(
+                    concat!(
+                        "        GAME OVER\n",
+                        "\n",
+                        "     reached zone {:02}\n",
+                        "     score {:06}\n",
+                        "     best combo {}\n",
+                        "\n",
+                        "  R  restart from zone 00\n",
+                        "  C  continue from zone {:02}, the score starts over\n",
+                        "\n",
+                        "{}",
+                    ),
+                    self.world.level,
+                    self.world.score.points,
+                    self.world.score.best_combo,
+                    self.world.level,
+                    self.high_score_text(),
                 ),
             );
 
+            ui.set_font(self.font, graphics::Scale{x: 24.0, y: 24.0});
+
+            let (uidx, uidy) = ui.dimensions(ctx);
+            let (uidx, uidy) = (uidx as f32, uidy as f32);
+            let (uix, uiy) = ((sc.w - uidx)/ 2.0, (sc.h - uidy)/2.0);
+            let (uix, uiy) = self.cam.cam2world(&sc, uix * self.cam.s, uiy * self.cam.s);
+            graphics::draw(
+                ctx,
+                &ui,
+                dp.dest([uix as f32, uiy as f32]).scale([1.0 / self.cam.s, 1.0 / self.cam.s]),
+            ).unwrap();
+        } else if self.world.death.is_some() {
+            let mut ui = graphics::Text::new(
+                match self.world.lives {
+                    Some(l) => format!("ship lost, {} left", l),
+                    None => "ship lost".to_string(),
+                }
+            );
+
             ui.set_font(self.font, graphics::Scale{x: 18.0, y: 18.0});
 
             let (uix, uiy) = self.cam.cam2world(&sc, 15.0 * self.cam.s, 15.0 * self.cam.s);
@@ -1773,6 +758,47 @@ impl ggez::event::EventHandler for State {
             ).unwrap();
         }
 
+        if let Some(ref e) = self.editor {
+            let mut ui = graphics::Text::new(format!("{}placing: {}\n", EDITOR_HELP, e.tool.name()));
+
+            ui.set_font(self.font, graphics::Scale{x: 18.0, y: 18.0});
+
+            let (uidx, _) = ui.dimensions(ctx);
+            let (uix, uiy) = self.cam.cam2world(&sc, (sc.w - (uidx as f32) - 15.0) * self.cam.s, 15.0 * self.cam.s);
+            graphics::draw(
+                ctx,
+                &ui,
+                dp.dest([uix as f32, uiy as f32]).scale([1.0 / self.cam.s, 1.0 / self.cam.s]),
+            ).unwrap();
+        }
+
+        // with or without a ship, a zone that might not be finishable says so
+        if let Some(ref e) = self.world.gen_error {
+            let mut ui = graphics::Text::new(e.to_string());
+
+            ui.set_font(self.font, graphics::Scale{x: 18.0, y: 18.0});
+
+            let (uix, uiy) = self.cam.cam2world(&sc, 15.0 * self.cam.s, (sc.h - 55.0) * self.cam.s);
+            graphics::draw(
+                ctx,
+                &ui,
+                dp.dest([uix as f32, uiy as f32]).scale([1.0 / self.cam.s, 1.0 / self.cam.s]),
+            ).unwrap();
+        }
+
+        if let Some(ref r) = self.replaying {
+            let mut ui = graphics::Text::new(if r.finished() { "replay finished" } else { "replay" });
+
+            ui.set_font(self.font, graphics::Scale{x: 18.0, y: 18.0});
+
+            let (uix, uiy) = self.cam.cam2world(&sc, 15.0 * self.cam.s, (sc.h - 30.0) * self.cam.s);
+            graphics::draw(
+                ctx,
+                &ui,
+                dp.dest([uix as f32, uiy as f32]).scale([1.0 / self.cam.s, 1.0 / self.cam.s]),
+            ).unwrap();
+        }
+
         graphics::present(ctx)?;
 
         // yield the CPU?
@@ -1789,8 +815,8 @@ impl ggez::event::EventHandler for State {
     }
 
     fn mouse_motion_event(&mut self, _ctx: &mut Context, x: f32, y: f32, _dx: f32, _dy: f32) {
-        self.input.mx = x;
-        self.input.my = y;
+        self.mx = x;
+        self.my = y;
     }
 
     fn mouse_wheel_event(&mut self, _ctx: &mut Context, _x: f32, y: f32) {
@@ -1807,56 +833,143 @@ impl ggez::event::EventHandler for State {
         self.cam.update = true;
     }
 
-    fn mouse_button_down_event(&mut self, _ctx: &mut Context, btn: input::mouse::MouseButton, _x: f32, _y: f32) {
+    fn mouse_button_down_event(&mut self, ctx: &mut Context, btn: input::mouse::MouseButton, _x: f32, _y: f32) {
+        if self.editor.is_some() {
+            let (mx, my) = self.mouse_world(ctx);
+            let e = self.editor.as_mut().unwrap();
+            match btn {
+                input::mouse::MouseButton::Left => e.press(&self.world, mx, my),
+                input::mouse::MouseButton::Right => e.delete(&self.world, mx, my),
+                _ => (),
+            }
+            return;
+        }
+
         match btn {
             input::mouse::MouseButton::Left => {
-                self.input.lmb = true;
+                self.world.input.lmb = true;
             },
             input::mouse::MouseButton::Right => {
-                self.input.rmb = true;
+                self.world.input.rmb = true;
             },
             _ => (),
         }
     }
 
     fn mouse_button_up_event(&mut self, _ctx: &mut Context, btn: input::mouse::MouseButton, _x: f32, _y: f32) {
+        if let Some(ref mut e) = self.editor {
+            if btn == input::mouse::MouseButton::Left {
+                e.release();
+            }
+            return;
+        }
+
         match btn {
             input::mouse::MouseButton::Left => {
-                self.input.lmb = false;
+                self.world.input.lmb = false;
             },
             input::mouse::MouseButton::Right => {
-                self.input.rmb = false;
+                self.world.input.rmb = false;
             },
             _ => (),
         }
     }
 
     fn key_down_event(&mut self, ctx: &mut Context, keycode: input::keyboard::KeyCode, _keymods: input::keyboard::KeyMods, _repeat: bool) {
+        if self.editor.is_some() {
+            let (mx, my) = self.mouse_world(ctx);
+            let e = self.editor.as_mut().unwrap();
+            let tool = match keycode {
+                input::keyboard::KeyCode::Key1 => Some(0),
+                input::keyboard::KeyCode::Key2 => Some(1),
+                input::keyboard::KeyCode::Key3 => Some(2),
+                input::keyboard::KeyCode::Key4 => Some(3),
+                input::keyboard::KeyCode::Key5 => Some(4),
+                input::keyboard::KeyCode::Key6 => Some(5),
+                input::keyboard::KeyCode::Key7 => Some(6),
+                input::keyboard::KeyCode::Key8 => Some(7),
+                _ => None,
+            };
+            if let Some(t) = tool {
+                e.tool = Kind::ALL[t];
+                return;
+            }
+            match keycode {
+                input::keyboard::KeyCode::T => {
+                    e.cycle_kind(&self.world, mx, my);
+                    return;
+                },
+                input::keyboard::KeyCode::LBracket => {
+                    e.resize(&self.world, mx, my, 1.0 / EDITOR_RESIZE);
+                    return;
+                },
+                input::keyboard::KeyCode::RBracket => {
+                    e.resize(&self.world, mx, my, EDITOR_RESIZE);
+                    return;
+                },
+                // no restarting from in here
+                input::keyboard::KeyCode::R => return,
+                _ => (),
+            }
+        }
+
+        // difficulty is picked before starting, a replay picks its own
+        if !self.world.started && self.replaying.is_none() {
+            let preset = match keycode {
+                input::keyboard::KeyCode::Key1 => Some(Preset::Easy),
+                input::keyboard::KeyCode::Key2 => Some(Preset::Normal),
+                input::keyboard::KeyCode::Key3 => Some(Preset::Hard),
+                _ => None,
+            };
+            if let Some(p) = preset {
+                self.world.preset = p;
+                return;
+            }
+        }
+
         match keycode {
             input::keyboard::KeyCode::Up |
             input::keyboard::KeyCode::W => {
-                self.input.up = true;
+                self.world.input.up = true;
             },
             input::keyboard::KeyCode::Down |
             input::keyboard::KeyCode::S => {
-                self.input.down = true;
+                self.world.input.down = true;
             },
             input::keyboard::KeyCode::Left |
             input::keyboard::KeyCode::A => {
-                self.input.left = true;
+                self.world.input.left = true;
             },
             input::keyboard::KeyCode::Right |
             input::keyboard::KeyCode::D => {
-                self.input.right = true;
+                self.world.input.right = true;
             },
             input::keyboard::KeyCode::Q => {
-                self.input.ccw = true;
+                self.world.input.ccw = true;
             },
             input::keyboard::KeyCode::E => {
-                self.input.cw = true;
+                self.world.input.cw = true;
             },
             input::keyboard::KeyCode::R => {
-                self.input.reset = true;
+                if !self.fixed_seed {
+                    self.world.seed = rand::thread_rng().gen();
+                }
+                self.world.input.reset = true;
+            },
+            input::keyboard::KeyCode::C => {
+                self.world.input.cont = true;
+            },
+            input::keyboard::KeyCode::F2 => {
+                self.export_level(ctx);
+            },
+            input::keyboard::KeyCode::F5 => {
+                self.save_run(ctx);
+            },
+            input::keyboard::KeyCode::F9 => {
+                self.resume_run(ctx);
+            },
+            input::keyboard::KeyCode::Tab => {
+                self.toggle_editor();
             },
             input::keyboard::KeyCode::Escape => {
                 event::quit(ctx);
@@ -1869,37 +982,40 @@ impl ggez::event::EventHandler for State {
         match keycode {
             input::keyboard::KeyCode::Up |
             input::keyboard::KeyCode::W => {
-                self.input.up = false;
+                self.world.input.up = false;
             },
             input::keyboard::KeyCode::Down |
             input::keyboard::KeyCode::S => {
-                self.input.down = false;
+                self.world.input.down = false;
             },
             input::keyboard::KeyCode::Left |
             input::keyboard::KeyCode::A => {
-                self.input.left = false;
+                self.world.input.left = false;
             },
             input::keyboard::KeyCode::Right |
             input::keyboard::KeyCode::D => {
-                self.input.right = false;
+                self.world.input.right = false;
             },
             input::keyboard::KeyCode::Q => {
-                self.input.ccw = false;
+                self.world.input.ccw = false;
             },
             input::keyboard::KeyCode::E => {
-                self.input.cw = false;
+                self.world.input.cw = false;
+            },
+            input::keyboard::KeyCode::C => {
+                self.world.input.cont = false;
             },
             input::keyboard::KeyCode::R => {
-                self.input.reset = false;
+                self.world.input.reset = false;
             },
             _ => (),
         };
     }
 }
 
-fn gen_fading_path(ctx: &mut Context, pts: &[[f32; 2]], s: f32, color: [f32;4]) -> DrawThing {
+fn gen_fading_path(ctx: &mut Context, pts: &[[f32; 2]], s: f32, color: [f32;4]) -> Option<graphics::Mesh> {
     if pts.len() < 3 {
-        return DrawThing::Blank;
+        return None;
     }
     let mut verts: Vec<graphics::Vertex> = Vec::new();
     let mut inds: Vec<u32> = Vec::new();
@@ -1972,90 +1088,86 @@ fn gen_fading_path(ctx: &mut Context, pts: &[[f32; 2]], s: f32, color: [f32;4])
         &inds,
         None,
     );
-    return DrawThing::Mesh(tm.unwrap());
+    return Some(tm.unwrap());
 }
 
-fn load_mesh(ctx: &mut Context, p: &str, scale: f32, color: [f32; 4]) -> (graphics::Mesh, f32) {
-    let f = filesystem::open(ctx, std::path::Path::new(p)).expect("Unable to find mesh file");
-
-    let f = BufReader::new(f);
-    // sort of parse a .obj file
-    let mut verts: Vec<graphics::Vertex> = Vec::new();
-    let mut inds: Vec<u32> = Vec::new();
+// the tuning file and prefabs, watched so edits apply while playing
+struct DataWatch {
+    dir: std::path::PathBuf, // the resources dir
+    stamp: Option<std::time::SystemTime>, // newest change seen
+    next_check: f64,
+}
 
-    let mut uvs: Vec<[f32; 2]> = Vec::new();
+impl DataWatch {
+    // when any of the files last changed
+    fn newest(&self) -> Option<std::time::SystemTime> {
+        let mut paths = vec![self.dir.join("tuning.toml"), self.dir.join("difficulty.toml")];
+        if let Ok(rd) = std::fs::read_dir(self.dir.join("prefabs")) {
+            paths.extend(rd.filter_map(|e| e.ok()).map(|e| e.path()));
+        }
+        return paths.iter().filter_map(
+            |p| std::fs::metadata(p).and_then(|m| m.modified()).ok()
+        ).max();
+    }
 
-    let mut rad = 0.0;
+    // all of them load or none do, and the world keeps a hash of the text for replays and saves
+    fn load(&self, world: &mut World) -> std::io::Result<()> {
+        let mut hash = TextHash::new();
 
-    for line in f.lines() {
-        if let Ok(l) = line { 
-            let mut i = l.split_ascii_whitespace();
-            match i.next() {
-                Some("v") => {
-                    let x = i.next().unwrap();
-                    let y = i.next().unwrap();
+        let tp = self.dir.join("tuning.toml");
+        let tuning = if tp.exists() {
+            let text = std::fs::read_to_string(&tp)?;
+            hash.add("tuning", &text);
+            Tuning::parse(&text)?
+        } else {
+            Tuning::default()
+        };
 
-                    let x : f32 = x.parse::<f32>().unwrap() * scale;
-                    let y : f32 = y.parse::<f32>().unwrap() * scale;
+        let dp = self.dir.join("difficulty.toml");
+        let difficulties = if dp.exists() {
+            let text = std::fs::read_to_string(&dp)?;
+            hash.add("difficulty", &text);
+            Difficulties::parse(&text)?
+        } else {
+            Difficulties::builtin()
+        };
 
-                    let r = ((x*x) + (y*y)).sqrt() as f32;
-                    if r > rad {
-                        rad = r;
-                    }
+        let mut prefabs = Prefabs::builtin();
+        let pd = self.dir.join("prefabs");
+        if pd.exists() {
+            prefabs.load_dir(&pd, &mut hash)?;
+        }
+        difficulties.check(&prefabs)?;
 
-                    let v = graphics::Vertex{
-                        pos: [x, -y],
-                        uv: [0.0, 0.0],
-                        color: color,
-                    };
+        world.prefabs = prefabs;
+        world.difficulties = difficulties;
+        world.data_hash = hash.finish();
+        world.retune(tuning);
+        return Ok(());
+    }
+}
 
-                    verts.push(v);
-                },
-                Some("vt") => {
-                    let x = i.next().unwrap();
-                    let y = i.next().unwrap();
+fn load_mesh(ctx: &mut Context, p: &str, scale: f32, color: [f32; 4]) -> (graphics::Mesh, f32) {
+    let f = filesystem::open(ctx, std::path::Path::new(p)).expect("Unable to find mesh file");
 
-                    let x : f32 = x.parse().unwrap();
-                    let y : f32 = y.parse().unwrap();
+    let md = parse_mesh(BufReader::new(f), scale, color);
 
-                    uvs.push([x,y]);
-                },
-                Some("f") => {
-                    let mut i1 = i.next().unwrap().split("/");
-                    let i1_v : u32 = i1.next().unwrap().parse::<u32>().expect("Mesh doesn't have uvs") - 1;
-                    let i1_uv : u32 = i1.next().unwrap().parse::<u32>().unwrap() - 1;
-
-                    let mut i2 = i.next().unwrap().split("/");
-                    let i2_v : u32 = i2.next().unwrap().parse::<u32>().unwrap() - 1;
-                    let i2_uv : u32 = i2.next().unwrap().parse::<u32>().unwrap() - 1;
-
-                    let mut i3 = i.next().unwrap().split("/");
-                    let i3_v : u32 = i3.next().unwrap().parse::<u32>().unwrap() - 1;
-                    let i3_uv :u32 = i3.next().unwrap().parse::<u32>().unwrap() - 1;
-
-                    // add the indicies
-                    inds.push(i1_v);
-                    inds.push(i2_v);
-                    inds.push(i3_v);
-
-                    // associate uvs with positions
-                    verts[i1_v as usize].uv = uvs[i1_uv as usize];
-                    verts[i2_v as usize].uv = uvs[i2_uv as usize];
-                    verts[i3_v as usize].uv = uvs[i3_uv as usize];
-                },
-                _ => (),
-            }
+    let verts: Vec<graphics::Vertex> = md.verts.iter().map(
+        |v| graphics::Vertex{
+            pos: v.pos,
+            uv: v.uv,
+            color: v.color,
         }
-    }
+    ).collect();
 
     return (
         graphics::Mesh::from_raw(
             ctx,
             &verts,
-            &inds,
+            &md.inds,
             None,
         ).unwrap(),
-        rad,
+        md.rad,
     );
 }
 
@@ -2063,17 +1175,101 @@ pub fn main() {
 
     println!("Game Starting");
 
+    // --seed N to replay the same run
+    // --record FILE to save the inputs of the session, --replay FILE to play them back
+    // --integrator euler|verlet|rk4 to pick how bodies are moved
+    // --theta X for the barnes-hut opening angle, --cutoff-gravity to cut stars off at their reach instead
+    // --level FILE to play a level file, give it again for the zones after
+    // --difficulty easy|normal|hard to start on that instead of picking on the start screen
+    // --new to leave the saved run alone and start at the start screen, it is resumed otherwise
+    let mut seed = None;
+    let mut preset = Preset::Normal;
+    let mut integrator = Integrator::Verlet;
+    let mut barnes_hut = Some(BH_THETA);
+    let mut record = None;
+    let mut replaying = None;
+    let mut level_paths = Vec::new();
+    let mut resume = true;
+    let mut args = std::env::args().skip(1);
+    while let Some(a) = args.next() {
+        match a.as_str() {
+            "--seed" => {
+                let v = args.next().expect("--seed needs a value");
+                seed = Some(v.parse::<u64>().expect("--seed must be a number"));
+            },
+            "--integrator" => {
+                let v = args.next().expect("--integrator needs a value");
+                integrator = Integrator::parse(&v).expect("--integrator must be euler, verlet or rk4");
+            },
+            "--theta" => {
+                let v = args.next().expect("--theta needs a value");
+                barnes_hut = Some(v.parse::<f64>().expect("--theta must be a number"));
+            },
+            "--cutoff-gravity" => {
+                barnes_hut = None;
+            },
+            "--level" => {
+                level_paths.push(args.next().expect("--level needs a file"));
+            },
+            "--difficulty" => {
+                let v = args.next().expect("--difficulty needs a value");
+                preset = Preset::from_name(&v).expect("--difficulty must be easy, normal or hard");
+            },
+            "--new" => {
+                resume = false;
+            },
+            "--record" => {
+                record = Some(std::path::PathBuf::from(args.next().expect("--record needs a file")));
+            },
+            "--replay" => {
+                let p = args.next().expect("--replay needs a file");
+                match Replay::load(std::path::Path::new(&p)) {
+                    Ok(r) => replaying = Some(r),
+                    Err(e) => {
+                        println!("Unable to load replay {}: {}", p, e);
+                        return;
+                    },
+                }
+            },
+            _ => {
+                println!("Unknown argument {}", a);
+            },
+        }
+    }
+
+    // a run given on the command line takes the place of the saved one
+    if seed.is_some() || !level_paths.is_empty() {
+        resume = false;
+    }
+
+    if let Some(ref r) = replaying {
+        level_paths = r.levels.clone();
+    }
+    let mut levels = Vec::new();
+    for p in level_paths {
+        match LevelFile::load(std::path::Path::new(&p)) {
+            Ok(l) => levels.push((p, l)),
+            Err(e) => {
+                println!("Unable to load level {}: {}", p, e);
+                return;
+            },
+        }
+    }
+
     let c = conf::Conf::new();
 
     let mut cb = ContextBuilder::new("rusty_raid", "Jordan9001").conf(c);
 
+    let mut res_dir = None;
     if let Ok(rdir) = std::env::var("CARGO_MANIFEST_DIR") {
         let mut path = std::path::PathBuf::from(rdir);
         path.push("resources");
+        res_dir = Some(path.clone());
         cb = cb.add_resource_path(path);
     }
     
     let (ref mut ctx, ref mut event_loop) = cb.build().unwrap();
+    let res_dir = res_dir.unwrap_or_else(|| filesystem::resources_dir(ctx).to_path_buf());
 
     //graphics::set_default_filter(ctx, graphics::FilterMode::Nearest);
     graphics::set_default_filter(ctx, graphics::FilterMode::Linear);
@@ -2089,11 +1285,69 @@ pub fn main() {
 
     graphics::set_window_title(ctx, GAME_NAME);
 
-    let mut state = State::new(ctx).unwrap();
+    let mut state = State::new(ctx, res_dir, seed, integrator, barnes_hut, record.is_some(), replaying, levels).unwrap();
+    state.world.preset = preset;
+
+    // a replay only plays back the same with the data it was recorded with
+    if let Some(h) = state.replaying.as_ref().and_then(|r| r.data) {
+        if h != state.world.data_hash {
+            println!("Unable to play the replay, it was recorded with other tuning, difficulty or prefab files");
+            return;
+        }
+    }
+
+    if resume {
+        state.resume_run(ctx);
+    }
 
     // generate a map
 
     event::run(ctx, event_loop, &mut state).unwrap();
 
+    // a run still going when the game closes is saved to carry on with next time
+    // and only counts as given up if it couldn't be, closing from the editor leaves the last save alone
+    if state.world.started && !state.world.game_over && state.editor.is_none() && !state.save_run(ctx) {
+        state.world.end_run();
+    }
+    state.take_runs(ctx);
+
+    if let (Some(p), Some(r)) = (&record, &state.recording) {
+        match r.save(p) {
+            Ok(()) => println!("Saved replay to {}", p.display()),
+            Err(e) => println!("Unable to save replay {}: {}", p.display(), e),
+        }
+    }
+
     println!("Done");
 }
+
+// a text file in the user data dir, name starts with a /
+fn write_user_file(ctx: &mut Context, name: &str, text: &str) -> std::io::Result<()> {
+    let mut f = filesystem::create(ctx, name).map_err(
+        |e| std::io::Error::new(std::io::ErrorKind::Other, format!("{:?}", e))
+    )?;
+    return f.write_all(text.as_bytes());
+}
+
+fn read_user_file(ctx: &mut Context, name: &str) -> std::io::Result<String> {
+    let mut f = filesystem::open(ctx, name).map_err(
+        |e| std::io::Error::new(std::io::ErrorKind::Other, format!("{:?}", e))
+    )?;
+    let mut text = String::new();
+    f.read_to_string(&mut text)?;
+    return Ok(text);
+}
+
+// the saved high scores, an empty table if there are none yet or they can't be read
+fn load_high_scores(ctx: &mut Context) -> HighScores {
+    if !filesystem::exists(ctx, HIGH_SCORE_FILE) {
+        return HighScores::default();
+    }
+    match read_user_file(ctx, HIGH_SCORE_FILE).and_then(|text| HighScores::parse(&text)) {
+        Ok(hs) => return hs,
+        Err(e) => {
+            println!("Starting a new high score table, {}", e);
+            return HighScores::default();
+        },
+    }
+}
@@ -0,0 +1,360 @@
+use std::collections::HashMap;
+use serde::Deserialize;
+use crate::entity::IdVal;
+use crate::world::mesh_index;
+use crate::util::{bad_data, TextHash};
+
+// entity templates, one toml file per prefab in resources/prefabs, named by the file
+// each section gives the entity that component, World::spawn_prefab puts it together
+//
+//   mesh = "pnd"          draw with this mesh (file name from MESHES), radii default to its size
+//   size = 100.0          or draw a circle this big, radii default to it
+//   role = "lock"         lock, portal or player, what the world keeps track of it as
+//   [grav]                mass, or mass_mul * size cubed, pulls out to where it drops below reach
+//   [dynamic]             moves under gravity, or with still = true only once a shockwave knocks it loose
+//   [collides]            hits colliders
+//   [collider]            gets hit, action is "none", "portal", { fuel = amt } or { explosion = { size, destroy_self } }
+//   [turret]              kind is "nuke", { flak = { speed } }, { mines = { count, spread } } or { beam = { dps, time, range } }
+//                         and shot the prefab it fires or lays
+//   [fuze]                goes off after travel, or once armed for arm seconds with the player within near
+//   [ship]                shot is the prefab it fires, blast lets its hull soak up explosions
+//   [pilot]               flies the ship, patrolling, chasing the player, dodging stars and running low on fuel
+//   [rocket] [trail] [prediction]
+
+// the ones the game is built from, files in the resources dir replace these or add more
+const BUILTIN: [(&str, &str); 17] = [
+    ("portal", include_str!("../resources/prefabs/portal.toml")),
+    ("lock", include_str!("../resources/prefabs/lock.toml")),
+    ("turret", include_str!("../resources/prefabs/turret.toml")),
+    ("fuel", include_str!("../resources/prefabs/fuel.toml")),
+    ("star", include_str!("../resources/prefabs/star.toml")),
+    ("big_star", include_str!("../resources/prefabs/big_star.toml")),
+    ("player", include_str!("../resources/prefabs/player.toml")),
+    ("player_nuke", include_str!("../resources/prefabs/player_nuke.toml")),
+    ("turret_nuke", include_str!("../resources/prefabs/turret_nuke.toml")),
+    ("beam_turret", include_str!("../resources/prefabs/beam_turret.toml")),
+    ("mine_turret", include_str!("../resources/prefabs/mine_turret.toml")),
+    ("mine", include_str!("../resources/prefabs/mine.toml")),
+    ("flak_turret", include_str!("../resources/prefabs/flak_turret.toml")),
+    ("flak_shell", include_str!("../resources/prefabs/flak_shell.toml")),
+    ("moon", include_str!("../resources/prefabs/moon.toml")),
+    ("fighter", include_str!("../resources/prefabs/fighter.toml")),
+    ("capital", include_str!("../resources/prefabs/capital.toml")),
+];
+
+#[derive(Deserialize, Clone, Copy, PartialEq, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum Role {
+    Lock,
+    Portal,
+    Player,
+}
+
+#[derive(Deserialize, Clone, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct PGrav {
+    pub mass: Option<f64>,
+    pub mass_mul: Option<f64>,
+    pub reach: Option<f64>, // no reach pulls everywhere
+}
+
+#[derive(Deserialize, Clone, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct PDynamic {
+    #[serde(default)]
+    pub vel: [f64; 2],
+    #[serde(default)]
+    pub still: bool, // stays put until a shockwave knocks it loose
+}
+
+#[derive(Deserialize, Clone, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct PCollides {
+    pub rad: Option<f64>,
+}
+
+#[derive(Deserialize, Clone, Copy, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum PAction {
+    None,
+    Portal,
+    Fuel(f64),
+    Explosion{size: f32, destroy_self: bool},
+}
+
+#[derive(Deserialize, Clone, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct PCollider {
+    pub rad: Option<f64>,
+    pub action: PAction,
+    #[serde(default)]
+    pub stop: bool, // blocks predictions and line of sight
+}
+
+#[derive(Deserialize, Clone, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct PShip {
+    pub thrust: f64,
+    pub empty_thrust: f64,
+    pub fuel: f64,
+    pub ammo: usize,
+    pub hull: Option<f64>, // SHIP_HULL if left out
+    #[serde(default)]
+    pub blast: f64, // hull lost a second inside an explosion, left out it goes up at once
+    pub shot: Option<String>, // player_nuke if left out
+}
+
+#[derive(Deserialize, Clone, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct PPilot {
+    pub speed: f64,
+    pub cruise: f64, // speed it patrols at
+    pub patrol: f64, // how far it wanders from where it was spawned
+    pub sight: f64,
+    pub range: f64, // fires from this close
+    pub keep: f64, // distance it holds from the player
+    pub fire_rate: f64, // seconds between shots
+    pub skill: Option<f64>, // how well its shots lead the player, 1 if left out
+    pub flee_fuel: f64, // runs for it below this much of its fuel, 0 to 1
+    pub look: f64, // seconds ahead it watches for stars
+    #[serde(default)]
+    pub boss: bool, // scores score_boss and shows its hull on the HUD
+}
+
+#[derive(Deserialize, Clone, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct PRocket {
+    pub thrust: f64,
+    pub nav_gain: Option<f64>, // how hard it steers to meet its target, NAV_GAIN if left out
+    pub turn_rate: Option<f64>, // radians a second, left out it turns at once
+    pub burn: Option<f64>, // seconds of thrust before it coasts, left out it never stops
+    pub fuze: Option<f64>, // goes off this close to its target, left out only on a hit
+}
+
+#[derive(Deserialize, Clone, Copy, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum PTurretKind {
+    Nuke,
+    Flak{speed: f64},
+    Mines{count: usize, spread: f64}, // spread is past the turret's edge
+    Beam{dps: f64, time: f64, range: f64},
+}
+
+impl Default for PTurretKind {
+    fn default() -> PTurretKind {
+        return PTurretKind::Nuke;
+    }
+}
+
+#[derive(Deserialize, Clone, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct PTurret {
+    #[serde(default)]
+    pub kind: PTurretKind,
+    pub shot: Option<String>, // turret_nuke if left out, beams don't fire anything
+    pub fire_rate: f64,
+    pub skill: Option<f64>, // how well it leads the player, 0 shoots where they are and 1 where they'll be, 1 if left out
+}
+
+#[derive(Deserialize, Clone, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct PFuze {
+    pub travel: Option<f64>, // left out only the near fuze, flak turrets set it for each shell
+    #[serde(default)]
+    pub near: f64, // 0 for none
+    #[serde(default)]
+    pub arm: f64,
+}
+
+#[derive(Deserialize, Clone, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct PTrail {
+    pub size: f32,
+    pub color: [f32; 4],
+}
+
+#[derive(Deserialize, Clone, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct PPrediction {
+    #[serde(default)]
+    pub drawable: bool,
+}
+
+#[derive(Deserialize, Clone, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct Prefab {
+    pub mesh: Option<String>,
+    pub size: Option<f64>,
+    #[serde(default)]
+    pub minsz: f32,
+    pub role: Option<Role>,
+    pub grav: Option<PGrav>,
+    pub dynamic: Option<PDynamic>,
+    pub collides: Option<PCollides>,
+    pub collider: Option<PCollider>,
+    pub ship: Option<PShip>,
+    pub rocket: Option<PRocket>,
+    pub turret: Option<PTurret>,
+    pub fuze: Option<PFuze>,
+    pub pilot: Option<PPilot>,
+    pub trail: Option<PTrail>,
+    pub prediction: Option<PPrediction>,
+}
+
+// what one spawn changes from its prefab
+#[derive(Default, Clone)]
+pub struct Overrides {
+    pub vel: Option<(f64, f64)>,
+    pub size: Option<f64>,
+    pub target: Option<IdVal>, // for a rocket to home in on
+}
+
+impl Overrides {
+    pub fn size(size: f64) -> Overrides {
+        return Overrides{size: Some(size), ..Default::default()};
+    }
+}
+
+pub struct Prefabs {
+    pub prefabs: HashMap<String, Prefab>,
+}
+
+impl Prefabs {
+    pub fn builtin() -> Prefabs {
+        let mut p = Prefabs{
+            prefabs: HashMap::new(),
+        };
+        for (name, text) in BUILTIN.iter() {
+            p.add(name, text).expect("bad builtin prefab");
+        }
+        p.check().expect("bad builtin prefab");
+        return p;
+    }
+
+    pub fn get(&self, name: &str) -> Option<&Prefab> {
+        return self.prefabs.get(name);
+    }
+
+    // parse one prefab file's text, replacing any prefab by that name
+    pub fn add(&mut self, name: &str, text: &str) -> std::io::Result<()> {
+        let p: Prefab = toml::from_str(text).map_err(
+            |e| bad_data(format!("prefab {}: {}", name, e))
+        )?;
+        if let Some(m) = &p.mesh {
+            if mesh_index(m).is_none() {
+                return Err(bad_data(format!("prefab {} has unknown mesh {}", name, m)));
+            }
+        }
+        if p.mesh.is_some() && p.size.is_some() {
+            return Err(bad_data(format!("prefab {} has both a mesh and a size", name)));
+        }
+        if let Some(s) = p.turret.as_ref().and_then(|t| t.skill) {
+            if !(s >= 0.0 && s <= 1.0) {
+                return Err(bad_data(format!("prefab {} has turret skill {}, it goes from 0 to 1", name, s)));
+            }
+        }
+        if let Some(rk) = &p.rocket {
+            for (field, v) in [("nav_gain", rk.nav_gain), ("turn_rate", rk.turn_rate), ("burn", rk.burn), ("fuze", rk.fuze)].iter() {
+                if let Some(v) = v {
+                    if !(*v >= 0.0) {
+                        return Err(bad_data(format!("prefab {} has rocket {} {}, it can't be below 0", name, field, v)));
+                    }
+                }
+            }
+        }
+        if let Some(t) = &p.turret {
+            let bad = match t.kind {
+                PTurretKind::Nuke => None,
+                PTurretKind::Flak{speed} => if speed > 0.0 { None } else { Some("flak speed") },
+                PTurretKind::Mines{count, spread} => if count > 0 && spread >= 0.0 { None } else { Some("mines count or spread") },
+                PTurretKind::Beam{dps, time, range} => if dps >= 0.0 && time > 0.0 && range > 0.0 { None } else { Some("beam dps, time or range") },
+            };
+            if let Some(what) = bad {
+                return Err(bad_data(format!("prefab {} has a turret {} out of range", name, what)));
+            }
+        }
+        if let Some(f) = &p.fuze {
+            if !(f.near >= 0.0 && f.arm >= 0.0 && f.travel.map_or(true, |t| t >= 0.0)) {
+                return Err(bad_data(format!("prefab {} has a fuze value below 0", name)));
+            }
+        }
+        if let Some(s) = &p.ship {
+            if !(s.blast >= 0.0) {
+                return Err(bad_data(format!("prefab {} has ship blast {}, it can't be below 0", name, s.blast)));
+            }
+        }
+        if let Some(pl) = &p.pilot {
+            if p.ship.is_none() || p.dynamic.is_none() {
+                return Err(bad_data(format!("prefab {} has a pilot, it needs [ship] and [dynamic] too", name)));
+            }
+            let vals = [pl.speed, pl.cruise, pl.patrol, pl.sight, pl.range, pl.keep, pl.fire_rate, pl.look];
+            if vals.iter().any(|v| !(*v >= 0.0)) {
+                return Err(bad_data(format!("prefab {} has a pilot value below 0", name)));
+            }
+            for (field, v) in [("skill", pl.skill.unwrap_or(1.0)), ("flee_fuel", pl.flee_fuel)].iter() {
+                if !(*v >= 0.0 && *v <= 1.0) {
+                    return Err(bad_data(format!("prefab {} has pilot {} {}, it goes from 0 to 1", name, field, v)));
+                }
+            }
+        }
+        if p.prediction.is_some() && p.dynamic.is_some() && p.collides.is_none() {
+            return Err(bad_data(format!("prefab {} has a prediction, it needs [collides] to see what it'd hit", name)));
+        }
+        if p.rocket.is_some() && p.dynamic.is_none() {
+            return Err(bad_data(format!("prefab {} has a rocket, it needs [dynamic] too", name)));
+        }
+        if p.role == Some(Role::Player) && (p.ship.is_none() || p.dynamic.is_none()) {
+            return Err(bad_data(format!("prefab {} is the player, it needs [ship] and [dynamic] too", name)));
+        }
+        if let Some(g) = &p.grav {
+            if g.mass.is_some() == g.mass_mul.is_some() {
+                return Err(bad_data(format!("prefab {} needs one of grav mass or mass_mul", name)));
+            }
+        }
+        self.prefabs.insert(name.to_string(), p);
+        return Ok(());
+    }
+
+    // every .toml in dir, named by the file, with the text of each going into hash
+    pub fn load_dir(&mut self, dir: &std::path::Path, hash: &mut TextHash) -> std::io::Result<()> {
+        let mut paths = Vec::new();
+        for e in std::fs::read_dir(dir)? {
+            paths.push(e?.path());
+        }
+        paths.sort();
+
+        for p in paths {
+            if p.extension().map_or(true, |e| e != "toml") {
+                continue;
+            }
+            let name = match p.file_stem().and_then(|s| s.to_str()) {
+                Some(n) => n.to_string(),
+                None => continue,
+            };
+            let text = std::fs::read_to_string(&p)?;
+            hash.add(&name, &text);
+            self.add(&name, &text)?;
+        }
+
+        return self.check();
+    }
+
+    // the prefabs that name other prefabs, once they're all in
+    pub fn check(&self) -> std::io::Result<()> {
+        for (name, p) in &self.prefabs {
+            if let Some(t) = &p.turret {
+                let shot = t.shot.as_deref().unwrap_or("turret_nuke");
+                if !self.prefabs.contains_key(shot) && !matches!(t.kind, PTurretKind::Beam{..}) {
+                    return Err(bad_data(format!("prefab {} fires {}, there's no prefab by that name", name, shot)));
+                }
+            }
+            if let Some(s) = &p.ship {
+                let shot = s.shot.as_deref().unwrap_or("player_nuke");
+                if !self.prefabs.contains_key(shot) {
+                    return Err(bad_data(format!("prefab {} fires {}, there's no prefab by that name", name, shot)));
+                }
+            }
+        }
+        return Ok(());
+    }
+}
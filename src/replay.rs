@@ -0,0 +1,205 @@
+use std::io::{BufReader, BufRead, BufWriter, Write};
+use crate::world::World;
+use crate::integrate::Integrator;
+use crate::barnes_hut::BH_THETA;
+use crate::difficulty::Preset;
+use crate::util::{bad_data, parse_field};
+
+// recorded inputs for every simulation tick, so a run can be fed back in exactly
+//
+// the file is plain text, one item per line
+//   version <game version>
+//   seed <run seed>
+//   integrator <euler|verlet|rk4>
+//   gravity <bh theta|cutoff>
+//   data <hash>                      of the tuning, difficulty and prefab files it was recorded with
+//   level <path>                     a level file the run was played with, in zone order
+//   s <seed>                         the run seed changed (restart with a new seed)
+//   d <easy|normal|hard>             the difficulty changed, it is normal until the first of these
+//   t <dt> <mx> <my> <lmb> <rmb> <reset> <cont>
+
+pub const GAME_VERSION: &str = env!("CARGO_PKG_VERSION");
+
+pub struct TickInput {
+    pub dt: f64,
+    pub mx: f64,
+    pub my: f64,
+    pub lmb: bool,
+    pub rmb: bool,
+    pub reset: bool,
+    pub cont: bool,
+    pub seed: u64,
+    pub preset: Preset,
+}
+
+pub struct Replay {
+    pub version: String,
+    pub seed: u64,
+    pub integrator: Integrator,
+    pub barnes_hut: Option<f64>, // opening angle, or None for cut off gravity
+    pub levels: Vec<String>, // level files, the replay needs the same ones
+    pub data: Option<u64>, // World::data_hash it was recorded with, None if the file doesn't say
+    pub ticks: Vec<TickInput>,
+    pub pos: usize, // next tick to play back
+}
+
+impl Replay {
+    pub fn new(seed: u64, integrator: Integrator, barnes_hut: Option<f64>) -> Replay {
+        Replay{
+            version: GAME_VERSION.to_string(),
+            seed,
+            integrator,
+            barnes_hut,
+            levels: Vec::new(),
+            data: None,
+            ticks: Vec::new(),
+            pos: 0,
+        }
+    }
+
+    // call before stepping the world, with the inputs it is about to use
+    pub fn record(&mut self, w: &World, dt: f64) {
+        self.ticks.push(
+            TickInput{
+                dt,
+                mx: w.input.mx,
+                my: w.input.my,
+                lmb: w.input.lmb,
+                rmb: w.input.rmb,
+                reset: w.input.reset,
+                cont: w.input.cont,
+                seed: w.seed,
+                preset: w.preset,
+            }
+        );
+    }
+
+    // load the next tick into the world, gives the dt to step with or None when done
+    pub fn play(&mut self, w: &mut World) -> Option<f64> {
+        let t = self.ticks.get(self.pos)?;
+        self.pos += 1;
+
+        w.input.mx = t.mx;
+        w.input.my = t.my;
+        w.input.lmb = t.lmb;
+        w.input.rmb = t.rmb;
+        w.input.reset = t.reset;
+        w.input.cont = t.cont;
+        w.seed = t.seed;
+        w.preset = t.preset;
+
+        return Some(t.dt);
+    }
+
+    pub fn finished(&self) -> bool {
+        return self.pos >= self.ticks.len();
+    }
+
+    pub fn save(&self, path: &std::path::Path) -> std::io::Result<()> {
+        let mut f = BufWriter::new(std::fs::File::create(path)?);
+
+        writeln!(f, "version {}", self.version)?;
+        writeln!(f, "seed {}", self.seed)?;
+        writeln!(f, "integrator {}", self.integrator.name())?;
+        match self.barnes_hut {
+            Some(theta) => writeln!(f, "gravity bh {}", theta)?,
+            None => writeln!(f, "gravity cutoff")?,
+        }
+        if let Some(h) = self.data {
+            writeln!(f, "data {}", h)?;
+        }
+        for l in &self.levels {
+            writeln!(f, "level {}", l)?;
+        }
+
+        let mut seed = self.seed;
+        let mut preset = Preset::Normal;
+        for t in &self.ticks {
+            if t.seed != seed {
+                seed = t.seed;
+                writeln!(f, "s {}", seed)?;
+            }
+            if t.preset != preset {
+                preset = t.preset;
+                writeln!(f, "d {}", preset.name())?;
+            }
+            writeln!(f, "t {} {} {} {} {} {} {}", t.dt, t.mx, t.my, t.lmb as u8, t.rmb as u8, t.reset as u8, t.cont as u8)?;
+        }
+
+        return f.flush();
+    }
+
+    pub fn load(path: &std::path::Path) -> std::io::Result<Replay> {
+        let f = BufReader::new(std::fs::File::open(path)?);
+
+        let mut r = Replay::new(0, Integrator::Verlet, Some(BH_THETA));
+        let mut seed = None;
+        let mut preset = Preset::Normal;
+
+        for (n, line) in f.lines().enumerate() {
+            let l = line?;
+            let mut i = l.split_ascii_whitespace();
+            let bad = || bad_data(format!("replay line {} is malformed: {}", n + 1, l));
+            match i.next() {
+                Some("version") => {
+                    r.version = i.next().ok_or_else(bad)?.to_string();
+                    if r.version != GAME_VERSION {
+                        return Err(bad_data(format!("replay is from version {}, this is {}", r.version, GAME_VERSION)));
+                    }
+                },
+                Some("seed") => {
+                    let s = parse_field(i.next()).ok_or_else(bad)?;
+                    r.seed = s;
+                    seed = Some(s);
+                },
+                Some("integrator") => {
+                    r.integrator = Integrator::parse(i.next().ok_or_else(bad)?).ok_or_else(bad)?;
+                },
+                Some("gravity") => {
+                    r.barnes_hut = match i.next() {
+                        Some("bh") => Some(parse_field(i.next()).ok_or_else(bad)?),
+                        Some("cutoff") => None,
+                        _ => return Err(bad()),
+                    };
+                },
+                Some("data") => {
+                    r.data = Some(parse_field(i.next()).ok_or_else(bad)?);
+                },
+                Some("level") => {
+                    let p = l.splitn(2, ' ').nth(1).ok_or_else(bad)?;
+                    r.levels.push(p.to_string());
+                },
+                Some("s") => {
+                    seed = Some(parse_field(i.next()).ok_or_else(bad)?);
+                },
+                Some("d") => {
+                    preset = i.next().and_then(Preset::from_name).ok_or_else(bad)?;
+                },
+                Some("t") => {
+                    let s = seed.ok_or_else(|| bad_data("replay has ticks before its seed".to_string()))?;
+                    r.ticks.push(
+                        TickInput{
+                            dt: parse_field(i.next()).ok_or_else(bad)?,
+                            mx: parse_field(i.next()).ok_or_else(bad)?,
+                            my: parse_field(i.next()).ok_or_else(bad)?,
+                            lmb: parse_field::<u8>(i.next()).ok_or_else(bad)? != 0,
+                            rmb: parse_field::<u8>(i.next()).ok_or_else(bad)? != 0,
+                            reset: parse_field::<u8>(i.next()).ok_or_else(bad)? != 0,
+                            cont: parse_field::<u8>(i.next()).ok_or_else(bad)? != 0,
+                            seed: s,
+                            preset,
+                        }
+                    );
+                },
+                None => (),
+                _ => return Err(bad()),
+            }
+        }
+
+        if seed.is_none() {
+            return Err(bad_data("replay has no seed".to_string()));
+        }
+
+        return Ok(r);
+    }
+}
@@ -0,0 +1,64 @@
+use rand::{RngCore, SeedableRng};
+use rand::rngs::SmallRng;
+
+// the world's random numbers, a SmallRng that counts what it has handed out
+// so a save can keep just the seed and the count and get back to the same place
+
+#[derive(Clone, Debug)]
+pub struct SimRng {
+    seed: u64,
+    draws: u64, // next_u64 calls since seeding, everything else is made of those
+    rng: SmallRng,
+}
+
+impl SimRng {
+    pub fn new(seed: u64) -> SimRng {
+        return SimRng::at(seed, 0);
+    }
+
+    // the rng that was seeded with seed and has since made draws
+    pub fn at(seed: u64, draws: u64) -> SimRng {
+        let mut r = SimRng{
+            seed,
+            draws: 0,
+            rng: SmallRng::seed_from_u64(seed),
+        };
+        for _ in 0..draws {
+            r.next_u64();
+        }
+        return r;
+    }
+
+    pub fn seed(&self) -> u64 {
+        return self.seed;
+    }
+
+    pub fn draws(&self) -> u64 {
+        return self.draws;
+    }
+}
+
+impl RngCore for SimRng {
+    fn next_u32(&mut self) -> u32 {
+        // what SmallRng does anyway, going through next_u64 keeps the count right
+        return self.next_u64() as u32;
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        self.draws += 1;
+        return self.rng.next_u64();
+    }
+
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        // eight bytes a draw, the same as SmallRng
+        for chunk in dest.chunks_mut(8) {
+            let b = self.next_u64().to_le_bytes();
+            chunk.copy_from_slice(&b[..chunk.len()]);
+        }
+    }
+
+    fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), rand::Error> {
+        self.fill_bytes(dest);
+        return Ok(());
+    }
+}
@@ -0,0 +1,312 @@
+use serde::{Deserialize, Serialize};
+use crate::world::*;
+use crate::entity::Entities;
+use crate::integrate::Integrator;
+use crate::difficulty::Preset;
+use crate::level::LevelFile;
+use crate::levelgen::{Palette, GENERATORS};
+use crate::score::Score;
+use crate::rng::SimRng;
+use crate::replay::GAME_VERSION;
+use crate::util::bad_data;
+
+// a run saved part way through a zone, so closing the game doesn't lose it
+//
+// the file is toml, the world's own state and then an [[entity]] table for every entity
+// with whichever components it has
+//
+//   version = 1
+//   data = "1469598103934665603"
+//   level = 3
+//   seed = "8154023315112392449"
+//   ...
+//   [[entity]]
+//   id = { index = 4, gen = 0 }
+//   pos = { x = 1200.0, y = -300.0, a = 0.0 }
+//   ship = { thrust = 69.0, empty_thrust = 12.0, fuel = 830.5, ammo = 12, hull = 100.0, max_hull = 100.0, blast = 0.0, shot = "player_nuke" }
+//
+// the tuning, prefabs and difficulties come from their own files like always, data is a hash of them
+// so resuming with different ones can say so
+// version goes up whenever the layout changes, parse says which versions it can't read
+
+pub const SAVE_VERSION: u32 = 7;
+
+#[derive(Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct EntitySave {
+    pub id: IdVal,
+    pub pos: Option<CPos>,
+    pub grav: Option<CGrav>,
+    pub dynamic: Option<CDynamic>,
+    pub collider: Option<CCollider>,
+    pub collides: Option<CCollides>,
+    pub drawable: Option<CDrawable>,
+    pub trail: Option<CTrail>,
+    pub prediction: Option<CPredictable>,
+    pub ship: Option<CShip>,
+    pub explosion: Option<CExplosion>,
+    pub rocket: Option<CRocket>,
+    pub turret: Option<CTurret>,
+    pub fuze: Option<CFuze>,
+    pub orbit: Option<COrbit>,
+    pub pilot: Option<CPilot>,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Save {
+    pub version: u32,
+    pub game: String, // version of the game that wrote it
+    #[serde(with = "as_text")]
+    pub data: u64, // World::data_hash when it was saved
+
+    #[serde(with = "as_text")]
+    pub seed: u64,
+    pub preset: Preset,
+    pub integrator: String,
+    pub barnes_hut: Option<f64>, // opening angle, or None for cut off gravity
+    pub use_grid: bool,
+    pub levels: Vec<LevelFile>,
+
+    pub level: usize,
+    pub theme: String,
+    pub palette: Palette,
+    pub spawn: (f64, f64),
+    #[serde(with = "as_text")]
+    pub rng_seed: u64,
+    pub rng_draws: u64,
+    pub started: bool,
+    pub finished: bool,
+    pub lives: Option<usize>,
+    pub death: Option<Death>,
+    pub game_over: bool,
+    pub score: Score,
+    pub next_chain: u32,
+    pub s_turret_next: f64,
+
+    pub playerid: Option<IdVal>,
+    pub portal: Option<IdVal>,
+    pub locks: Vec<IdVal>,
+    pub entities: Entities,
+    pub entity: Vec<EntitySave>,
+
+    pub cam: Option<(f64, f64, f32)>, // where the renderer was looking, it fills this in itself
+}
+
+impl Save {
+    pub fn from_world(w: &World) -> Save {
+        let entity = w.entities.iter().map(|id| EntitySave{
+            id,
+            pos: w.c_pos.get(&id).cloned(),
+            grav: w.c_grav.get(&id).cloned(),
+            dynamic: w.c_dynamic.get(&id).cloned(),
+            collider: w.c_collider.get(&id).cloned(),
+            collides: w.c_collides.get(&id).cloned(),
+            drawable: w.c_drawable.get(&id).cloned(),
+            trail: w.c_trail.get(&id).cloned(),
+            prediction: w.c_predictable.get(&id).cloned(),
+            ship: w.c_ship.get(&id).cloned(),
+            explosion: w.c_explosion.get(&id).cloned(),
+            rocket: w.c_rocket.get(&id).cloned(),
+            turret: w.c_turret.get(&id).cloned(),
+            fuze: w.c_fuze.get(&id).cloned(),
+            orbit: w.c_orbit.get(&id).cloned(),
+            pilot: w.c_pilot.get(&id).cloned(),
+        }).collect();
+
+        Save{
+            version: SAVE_VERSION,
+            game: GAME_VERSION.to_string(),
+            data: w.data_hash,
+
+            seed: w.seed,
+            preset: w.preset,
+            integrator: w.integrator.name().to_string(),
+            barnes_hut: if w.barnes_hut { Some(w.grav_tree.theta) } else { None },
+            use_grid: w.use_grid,
+            levels: w.levels.clone(),
+
+            level: w.level,
+            theme: w.theme.to_string(),
+            palette: w.palette,
+            spawn: w.spawn,
+            rng_seed: w.rng.seed(),
+            rng_draws: w.rng.draws(),
+            started: w.started,
+            finished: w.finished,
+            lives: w.lives,
+            death: w.death.clone(),
+            game_over: w.game_over,
+            score: w.score.clone(),
+            next_chain: w.next_chain,
+            s_turret_next: w.s_turret_next,
+
+            playerid: w.playerid,
+            portal: w.portal,
+            locks: w.locks.clone(),
+            entities: w.entities.clone(),
+            entity,
+
+            cam: None,
+        }
+    }
+
+    // put the world back how it was, checking the ids hang together first so a bad file leaves it alone
+    pub fn restore(self, w: &mut World) -> std::io::Result<()> {
+        let integrator = Integrator::parse(&self.integrator).ok_or_else(
+            || bad_data(format!("save has integrator {}", self.integrator))
+        )?;
+        let theme = match GENERATORS.iter().find(|g| g.name() == self.theme) {
+            Some(g) => g.name(),
+            None if self.theme.is_empty() => "",
+            None => return Err(bad_data(format!("save has zone kind {}", self.theme))),
+        };
+
+        let mut ids = std::collections::HashSet::new();
+        for e in &self.entity {
+            if !self.entities.is_alive(&e.id) || !ids.insert(e.id) {
+                return Err(bad_data(format!("save has entity {}:{} more than once or not alive", e.id.index, e.id.gen)));
+            }
+        }
+        if self.entities.iter().count() != ids.len() {
+            return Err(bad_data("save has live entities with nothing saved for them".to_string()));
+        }
+        let refs = self.playerid.iter().chain(self.portal.iter()).chain(self.locks.iter());
+        for id in refs {
+            if !ids.contains(id) {
+                return Err(bad_data(format!("save refers to entity {}:{} that isn't there", id.index, id.gen)));
+            }
+        }
+
+        w.reset();
+
+        w.seed = self.seed;
+        w.preset = self.preset;
+        w.integrator = integrator;
+        w.barnes_hut = self.barnes_hut.is_some();
+        if let Some(theta) = self.barnes_hut {
+            w.grav_tree.theta = theta;
+        }
+        w.use_grid = self.use_grid;
+        w.levels = self.levels;
+
+        w.level = self.level;
+        w.gen_error = None;
+        w.theme = theme;
+        w.palette = self.palette;
+        w.spawn = self.spawn;
+        w.rng = SimRng::at(self.rng_seed, self.rng_draws);
+        w.started = self.started;
+        w.finished = self.finished;
+        w.lives = self.lives;
+        w.death = self.death;
+        w.game_over = self.game_over;
+        w.score = self.score;
+        w.ended_runs.clear();
+        w.next_chain = self.next_chain;
+        w.s_turret_next = self.s_turret_next;
+
+        w.playerid = self.playerid;
+        w.portal = self.portal;
+        w.locks = self.locks;
+        w.entities = self.entities;
+
+        for e in self.entity {
+            let id = e.id;
+            if let Some(c) = e.pos {
+                // nothing to draw between yet
+                w.prev_pos.insert(id, (c.x, c.y, c.a));
+                w.c_pos.insert(id, c);
+            }
+            if let Some(c) = e.grav { w.c_grav.insert(id, c); }
+            if let Some(c) = e.dynamic { w.c_dynamic.insert(id, c); }
+            if let Some(c) = e.collider { w.c_collider.insert(id, c); }
+            if let Some(c) = e.collides { w.c_collides.insert(id, c); }
+            if let Some(c) = e.drawable { w.c_drawable.insert(id, c); }
+            if let Some(c) = e.trail { w.c_trail.insert(id, c); }
+            if let Some(c) = e.prediction { w.c_predictable.insert(id, c); }
+            if let Some(c) = e.ship { w.c_ship.insert(id, c); }
+            if let Some(c) = e.explosion { w.c_explosion.insert(id, c); }
+            if let Some(c) = e.rocket { w.c_rocket.insert(id, c); }
+            if let Some(c) = e.turret { w.c_turret.insert(id, c); }
+            if let Some(c) = e.fuze { w.c_fuze.insert(id, c); }
+            if let Some(c) = e.orbit { w.c_orbit.insert(id, c); }
+            if let Some(c) = e.pilot { w.c_pilot.insert(id, c); }
+        }
+
+        return Ok(());
+    }
+
+    pub fn parse(text: &str) -> std::io::Result<Save> {
+        // just the version first, so a save from another version gets a message about that
+        // and not about whatever field changed
+        #[derive(Deserialize)]
+        struct Header {
+            version: u32,
+            game: String,
+        }
+        let h: Header = toml::from_str(text).map_err(|e| bad_data(format!("save: {}", e)))?;
+        if h.version != SAVE_VERSION {
+            let age = if h.version > SAVE_VERSION { "newer" } else { "older" };
+            return Err(bad_data(format!(
+                "save is format version {} from game {}, {} than this game's version {} ({})",
+                h.version, h.game, age, SAVE_VERSION, GAME_VERSION,
+            )));
+        }
+
+        return toml::from_str(text).map_err(|e| bad_data(format!("save: {}", e)));
+    }
+
+    pub fn load(path: &std::path::Path) -> std::io::Result<Save> {
+        return Save::parse(&std::fs::read_to_string(path)?);
+    }
+
+    pub fn save(&self, path: &std::path::Path) -> std::io::Result<()> {
+        return std::fs::write(path, self.to_string());
+    }
+}
+
+impl std::fmt::Display for Save {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        // through a toml::Value, which puts plain values ahead of tables however the fields are ordered
+        let v = toml::Value::try_from(self).expect("save always serializes");
+        write!(f, "{}", toml::to_string(&v).expect("save always serializes"))
+    }
+}
+
+// toml integers stop at i64, and seeds use all of a u64
+pub mod as_text {
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(v: &u64, s: S) -> Result<S::Ok, S::Error> {
+        return s.serialize_str(&v.to_string());
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(d: D) -> Result<u64, D::Error> {
+        let t = String::deserialize(d)?;
+        return t.parse().map_err(serde::de::Error::custom);
+    }
+}
+
+// toml keys have to be strings, so maps with other keys go out as a sorted list of pairs
+pub mod as_pairs {
+    use std::collections::HashMap;
+    use std::hash::Hash;
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+    pub fn serialize<K: Serialize + Ord, V: Serialize, S: Serializer>(m: &HashMap<K, V>, s: S) -> Result<S::Ok, S::Error> {
+        let mut v: Vec<(&K, &V)> = m.iter().collect();
+        v.sort_by(|a, b| a.0.cmp(b.0));
+        return v.serialize(s);
+    }
+
+    pub fn deserialize<'de, K, V, D>(d: D) -> Result<HashMap<K, V>, D::Error>
+    where
+        K: Deserialize<'de> + Eq + Hash,
+        V: Deserialize<'de>,
+        D: Deserializer<'de>,
+    {
+        let v: Vec<(K, V)> = Vec::deserialize(d)?;
+        return Ok(v.into_iter().collect());
+    }
+}
@@ -0,0 +1,199 @@
+use std::collections::HashMap;
+use serde::{Deserialize, Serialize};
+use crate::difficulty::Preset;
+use crate::save::{as_text, as_pairs};
+use crate::util::{bad_data, parse_field};
+
+// points for a run and the table of the best ones, which the game keeps in the user data dir
+//
+// the table is plain text, one run per line, best first
+//   <points> <zone reached> <easy|normal|hard> <seed> <yyyy-mm-dd>
+
+pub const HIGH_SCORES: usize = 10; // runs kept in the table
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct Score {
+    pub points: u64,
+    pub locks: usize,
+    pub turrets: usize,
+    pub ships: usize, // enemy ships, the boss included
+    pub zones: usize, // cleared
+    pub best_combo: usize, // most kills from one explosion chain
+    #[serde(with = "as_text")]
+    pub seed: u64,
+    pub preset: Preset,
+    pub zone: usize, // reached, set when the run ends
+
+    pub zone_time: f64, // seconds spent in the current zone
+    #[serde(with = "as_pairs")]
+    pub chain_kills: HashMap<u32, usize>, // kills so far by each explosion chain in the zone
+}
+
+impl Score {
+    pub fn new(seed: u64, preset: Preset) -> Score {
+        Score{
+            points: 0,
+            locks: 0,
+            turrets: 0,
+            ships: 0,
+            zones: 0,
+            best_combo: 0,
+            seed,
+            preset,
+            zone: 0,
+            zone_time: 0.0,
+            chain_kills: HashMap::new(),
+        }
+    }
+
+    // a kill by an explosion chain, worth its points plus the combo for the kills the chain made before it
+    pub fn kill(&mut self, points: f64, chain: u32, combo: f64) {
+        let n = self.chain_kills.entry(chain).or_default();
+        *n += 1;
+        let n = *n;
+        self.best_combo = self.best_combo.max(n);
+        self.add(points + (combo * ((n - 1) as f64)));
+    }
+
+    pub fn add(&mut self, points: f64) {
+        self.points += points.max(0.0).round() as u64;
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct HighScore {
+    pub points: u64,
+    pub zone: usize,
+    pub preset: Preset,
+    pub seed: u64,
+    pub date: String,
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct HighScores {
+    pub entries: Vec<HighScore>, // best first
+}
+
+impl HighScores {
+    // put a run in the table, gives its place if it was good enough to stay
+    pub fn add(&mut self, h: HighScore) -> Option<usize> {
+        // a tie goes below the runs already there
+        let i = self.entries.iter().position(|e| e.points < h.points).unwrap_or(self.entries.len());
+        if i >= HIGH_SCORES {
+            return None;
+        }
+        self.entries.insert(i, h);
+        self.entries.truncate(HIGH_SCORES);
+        return Some(i);
+    }
+
+    pub fn parse(text: &str) -> std::io::Result<HighScores> {
+        let mut hs = HighScores::default();
+        for (n, l) in text.lines().enumerate() {
+            let mut i = l.split_ascii_whitespace();
+            let bad = || bad_data(format!("high score line {} is malformed: {}", n + 1, l));
+            let points = match i.next() {
+                Some(p) => p.parse().map_err(|_| bad())?,
+                None => continue,
+            };
+            let h = HighScore{
+                points,
+                zone: parse_field(i.next()).ok_or_else(bad)?,
+                preset: i.next().and_then(Preset::from_name).ok_or_else(bad)?,
+                seed: parse_field(i.next()).ok_or_else(bad)?,
+                date: i.next().ok_or_else(bad)?.to_string(),
+            };
+            hs.add(h);
+        }
+        return Ok(hs);
+    }
+}
+
+impl std::fmt::Display for HighScores {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        for e in &self.entries {
+            writeln!(f, "{} {} {} {} {}", e.points, e.zone, e.preset.name(), e.seed, e.date)?;
+        }
+        return Ok(());
+    }
+}
+
+// yyyy-mm-dd for seconds since 1970, in UTC
+pub fn date(unix_secs: u64) -> String {
+    // days to a civil date, from howard hinnant's chrono algorithms
+    let z = (unix_secs / 86400) as i64 + 719468;
+    let era = z.div_euclid(146097);
+    let doe = z - (era * 146097);
+    let yoe = (doe - (doe / 1460) + (doe / 36524) - (doe / 146096)) / 365;
+    let doy = doe - ((365 * yoe) + (yoe / 4) - (yoe / 100));
+    let mp = ((5 * doy) + 2) / 153;
+    let d = doy - (((153 * mp) + 2) / 5) + 1;
+    let m = if mp < 10 { mp + 3 } else { mp - 9 };
+    let y = yoe + (era * 400) + if m <= 2 { 1 } else { 0 };
+    return format!("{:04}-{:02}-{:02}", y, m, d);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn run(points: u64, seed: u64) -> HighScore {
+        return HighScore{points, zone: 3, preset: Preset::Hard, seed, date: date(1700000000)};
+    }
+
+    #[test]
+    fn table_round_trips() {
+        let mut hs = HighScores::default();
+        for (i, p) in [1200, 50, 99999, 1200, 0].iter().enumerate() {
+            hs.add(run(*p, (i as u64) * 0x1234_5678_9abc));
+        }
+        let text = hs.to_string();
+        let back = HighScores::parse(&text).unwrap();
+        assert_eq!(back.to_string(), text);
+        let points: Vec<u64> = back.entries.iter().map(|e| e.points).collect();
+        assert_eq!(points, vec![99999, 1200, 1200, 50, 0]);
+        // the tie stays in the order it was added
+        assert_eq!(back.entries[1].seed, 0);
+        assert_eq!(back.entries[2].seed, 3 * 0x1234_5678_9abc);
+    }
+
+    #[test]
+    fn table_keeps_the_best_ones() {
+        let mut hs = HighScores::default();
+        for i in 0..(HIGH_SCORES as u64) {
+            assert_eq!(hs.add(run(100 + i, i)), Some(0));
+        }
+        assert_eq!(hs.add(run(50, 0)), None);
+        assert_eq!(hs.add(run(105, 0)), Some(5));
+        assert_eq!(hs.entries.len(), HIGH_SCORES);
+        assert_eq!(hs.entries.last().unwrap().points, 101);
+    }
+
+    #[test]
+    fn malformed_lines_are_rejected() {
+        assert!(HighScores::parse("100 3 hard 1 2024-01-01\n\n").is_ok());
+        assert!(HighScores::parse("100 3 impossible 1 2024-01-01\n").is_err());
+        assert!(HighScores::parse("100 3 hard\n").is_err());
+        assert!(HighScores::parse("lots 3 hard 1 2024-01-01\n").is_err());
+    }
+
+    #[test]
+    fn chain_kills_add_up_the_combo() {
+        let mut s = Score::new(1, Preset::Normal);
+        // three kills in one chain, a kill in another, then the first chain again
+        s.kill(100.0, 1, 50.0);
+        s.kill(100.0, 1, 50.0);
+        s.kill(100.0, 1, 50.0);
+        s.kill(100.0, 2, 50.0);
+        s.kill(100.0, 1, 50.0);
+        assert_eq!(s.points, 100 + 150 + 200 + 100 + 250);
+        assert_eq!(s.best_combo, 4);
+    }
+
+    #[test]
+    fn dates_are_utc_days() {
+        assert_eq!(date(0), "1970-01-01");
+        assert_eq!(date(951782400), "2000-02-29");
+        assert_eq!(date(1700000000), "2023-11-14");
+    }
+}
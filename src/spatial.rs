@@ -0,0 +1,297 @@
+use std::collections::HashMap;
+use crate::entity::IdVal;
+
+// uniform grid for finding what is near a point, circle, or segment
+// things are put in every cell their circle touches, so a query only has to look at its own cells
+// things too big for that (infinite gravity reach) go in a list that every query returns
+
+pub const GRID_CELL: f64 = 1000.0;
+const GRID_MAX_SPAN: i64 = 64; // wider than this many cells and it goes in everywhere instead
+
+pub struct Grid {
+    cell: f64,
+    cells: HashMap<(i64, i64), Vec<IdVal>>,
+    everywhere: Vec<IdVal>,
+}
+
+impl Grid {
+    pub fn new(cell: f64) -> Grid {
+        Grid{
+            cell,
+            cells: HashMap::new(),
+            everywhere: Vec::new(),
+        }
+    }
+
+    pub fn clear(&mut self) {
+        // keep the cell vecs around so rebuilding every tick doesn't reallocate,
+        // but a cell nothing went in last time gets dropped so the map doesn't grow forever
+        self.cells.retain(|_, v| !v.is_empty());
+        for (_, v) in &mut self.cells {
+            v.clear();
+        }
+        self.everywhere.clear();
+    }
+
+    fn cell_of(&self, x: f64, y: f64) -> (i64, i64) {
+        return ((x / self.cell).floor() as i64, (y / self.cell).floor() as i64);
+    }
+
+    pub fn insert(&mut self, id: IdVal, x: f64, y: f64, r: f64) {
+        if !r.is_finite() {
+            self.everywhere.push(id);
+            return;
+        }
+
+        let (x1, y1) = self.cell_of(x - r, y - r);
+        let (x2, y2) = self.cell_of(x + r, y + r);
+        if (x2 - x1) > GRID_MAX_SPAN || (y2 - y1) > GRID_MAX_SPAN {
+            self.everywhere.push(id);
+            return;
+        }
+
+        for cx in x1..=x2 {
+            for cy in y1..=y2 {
+                self.cells.entry((cx, cy)).or_insert_with(Vec::new).push(id);
+            }
+        }
+    }
+
+    // everything whose circle might contain this point
+    pub fn at(&self, x: f64, y: f64) -> impl Iterator<Item=&IdVal> {
+        let c = match self.cells.get(&self.cell_of(x, y)) {
+            Some(v) => &v[..],
+            None => &[],
+        };
+        return c.iter().chain(self.everywhere.iter());
+    }
+
+    // everything whose circle might touch this circle, sorted by id with no repeats
+    pub fn query_circle(&self, x: f64, y: f64, r: f64, out: &mut Vec<IdVal>) {
+        out.clear();
+        out.extend(&self.everywhere);
+
+        let (x1, y1) = self.cell_of(x - r, y - r);
+        let (x2, y2) = self.cell_of(x + r, y + r);
+        for cx in x1..=x2 {
+            for cy in y1..=y2 {
+                if let Some(v) = self.cells.get(&(cx, cy)) {
+                    out.extend(v);
+                }
+            }
+        }
+
+        out.sort_unstable();
+        out.dedup();
+    }
+
+    // everything whose circle might touch the segment, sorted by id with no repeats
+    pub fn query_segment(&self, x1: f64, y1: f64, x2: f64, y2: f64, out: &mut Vec<IdVal>) {
+        out.clear();
+        out.extend(&self.everywhere);
+
+        // walk the cells the segment passes through
+        let (mut cx, mut cy) = self.cell_of(x1, y1);
+        let (ex, ey) = self.cell_of(x2, y2);
+        let dx = x2 - x1;
+        let dy = y2 - y1;
+        let step_x = if dx > 0.0 { 1 } else { -1 };
+        let step_y = if dy > 0.0 { 1 } else { -1 };
+
+        // t along the segment at which we cross into the next column or row
+        let next_t = |c: i64, step: i64, p: f64, d: f64| {
+            if d == 0.0 {
+                return std::f64::INFINITY;
+            }
+            let edge = if step > 0 { (c + 1) as f64 } else { c as f64 };
+            return ((edge * self.cell) - p) / d;
+        };
+        let mut t_x = next_t(cx, step_x, x1, dx);
+        let mut t_y = next_t(cy, step_y, y1, dy);
+        let dt_x = if dx == 0.0 { std::f64::INFINITY } else { self.cell / dx.abs() };
+        let dt_y = if dy == 0.0 { std::f64::INFINITY } else { self.cell / dy.abs() };
+
+        let n = (ex - cx).abs() + (ey - cy).abs();
+        for _ in 0..=n {
+            if let Some(v) = self.cells.get(&(cx, cy)) {
+                out.extend(v);
+            }
+            if t_x < t_y {
+                cx += step_x;
+                t_x += dt_x;
+            } else {
+                cy += step_y;
+                t_y += dt_y;
+            }
+        }
+
+        out.sort_unstable();
+        out.dedup();
+    }
+}
+
+// circle around a segment, as (center x, center y, radius)
+pub fn sweep_bounds(x1: f64, y1: f64, x2: f64, y2: f64) -> (f64, f64, f64) {
+    let dx = x2 - x1;
+    let dy = y2 - y1;
+    return ((x1 + x2) / 2.0, (y1 + y2) / 2.0, ((dx * dx) + (dy * dy)).sqrt() / 2.0);
+}
+
+// two circles moving in straight lines over a step, a from a0 to a1 and b from b0 to b1
+// gives the fraction of the step at which they first touch (r apart), if they do
+pub fn sweep_circles(a0: (f64, f64), a1: (f64, f64), b0: (f64, f64), b1: (f64, f64), r: f64) -> Option<f64> {
+    // work in a's frame, b moves from d at t=0 by v over the step
+    let dx = b0.0 - a0.0;
+    let dy = b0.1 - a0.1;
+    let vx = (b1.0 - b0.0) - (a1.0 - a0.0);
+    let vy = (b1.1 - b0.1) - (a1.1 - a0.1);
+
+    // |d + v t|^2 = r^2
+    let c = (dx * dx) + (dy * dy) - (r * r);
+    if c <= 0.0 {
+        // already touching at the start
+        return Some(0.0);
+    }
+    let a = (vx * vx) + (vy * vy);
+    if a == 0.0 {
+        return None;
+    }
+    let b = (dx * vx) + (dy * vy);
+    if b >= 0.0 {
+        // moving apart
+        return None;
+    }
+    let disc = (b * b) - (a * c);
+    if disc < 0.0 {
+        return None;
+    }
+
+    let t = (-b - disc.sqrt()) / a;
+    if t > 1.0 {
+        return None;
+    }
+    return Some(t);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::Rng;
+    use crate::rng::SimRng;
+
+    // random circles, some small, some spanning many cells and some pulling everywhere
+    fn circles(rng: &mut SimRng, n: usize) -> Vec<(IdVal, f64, f64, f64)> {
+        return (0..n).map(|i| {
+            let r = match i % 10 {
+                0 => std::f64::INFINITY,
+                1 => rng.gen_range(1000.0, 80000.0),
+                _ => rng.gen_range(1.0, 1500.0),
+            };
+            (IdVal{index: i as u32, gen: 0}, rng.gen_range(-20000.0, 20000.0), rng.gen_range(-20000.0, 20000.0), r)
+        }).collect();
+    }
+
+    fn filled(cs: &[(IdVal, f64, f64, f64)]) -> Grid {
+        let mut g = Grid::new(GRID_CELL);
+        for (id, x, y, r) in cs {
+            g.insert(*id, *x, *y, *r);
+        }
+        return g;
+    }
+
+    fn seg_dist(px: f64, py: f64, x1: f64, y1: f64, x2: f64, y2: f64) -> f64 {
+        let (dx, dy) = (x2 - x1, y2 - y1);
+        let l2 = (dx * dx) + (dy * dy);
+        let t = if l2 == 0.0 { 0.0 } else { (((px - x1) * dx) + ((py - y1) * dy)) / l2 };
+        let t = t.max(0.0).min(1.0);
+        return ((px - (x1 + (t * dx))).powi(2) + (py - (y1 + (t * dy))).powi(2)).sqrt();
+    }
+
+    #[test]
+    fn query_circle_finds_everything_brute_force_does() {
+        let mut rng = SimRng::new(7);
+        let cs = circles(&mut rng, 300);
+        let g = filled(&cs);
+        let mut out = Vec::new();
+        for _ in 0..200 {
+            let (x, y, r) = (rng.gen_range(-25000.0, 25000.0), rng.gen_range(-25000.0, 25000.0), rng.gen_range(0.0, 3000.0));
+            g.query_circle(x, y, r, &mut out);
+            assert!(out.windows(2).all(|w| w[0] < w[1]));
+            for (id, cx, cy, cr) in &cs {
+                if ((cx - x).powi(2) + (cy - y).powi(2)).sqrt() <= (cr + r) {
+                    assert!(out.contains(id), "circle {:?} touches {}, {} but was missed", id, x, y);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn query_segment_finds_everything_brute_force_does() {
+        let mut rng = SimRng::new(11);
+        let cs = circles(&mut rng, 300);
+        let g = filled(&cs);
+        let mut out = Vec::new();
+        for i in 0..200 {
+            let (x1, y1) = (rng.gen_range(-25000.0, 25000.0), rng.gen_range(-25000.0, 25000.0));
+            // some straight along a row or column, some too short to leave their cell
+            let (x2, y2) = match i % 4 {
+                0 => (x1, rng.gen_range(-25000.0, 25000.0)),
+                1 => (rng.gen_range(-25000.0, 25000.0), y1),
+                2 => (x1 + rng.gen_range(-50.0, 50.0), y1 + rng.gen_range(-50.0, 50.0)),
+                _ => (rng.gen_range(-25000.0, 25000.0), rng.gen_range(-25000.0, 25000.0)),
+            };
+            g.query_segment(x1, y1, x2, y2, &mut out);
+            assert!(out.windows(2).all(|w| w[0] < w[1]));
+            for (id, cx, cy, cr) in &cs {
+                if seg_dist(*cx, *cy, x1, y1, x2, y2) <= *cr {
+                    assert!(out.contains(id), "circle {:?} touches {}, {} to {}, {} but was missed", id, x1, y1, x2, y2);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn cells_left_empty_get_dropped() {
+        let mut g = Grid::new(GRID_CELL);
+        let id = IdVal{index: 0, gen: 0};
+        // something flying along, in a new cell every rebuild
+        for i in 0..1000 {
+            g.clear();
+            g.insert(id, ((i as f64) * GRID_CELL * 3.0) + 500.0, 500.0, 10.0);
+        }
+        // the cell it's in now and the one from the build before, still held to reuse
+        assert!(g.cells.len() <= 2);
+        let mut out = Vec::new();
+        g.query_circle((999.0 * GRID_CELL * 3.0) + 500.0, 500.0, 1.0, &mut out);
+        assert_eq!(out, vec![id]);
+    }
+
+    #[test]
+    fn fast_circle_hits_a_thin_one_it_jumps_over() {
+        // both ends of the step are well clear, only the sweep sees it
+        let t = sweep_circles((-1000.0, 0.0), (1000.0, 0.0), (0.0, 0.0), (0.0, 0.0), 10.0).unwrap();
+        assert!((t - 0.495).abs() < 1e-12);
+        // the same with the other one moving instead
+        let t = sweep_circles((0.0, 0.0), (0.0, 0.0), (1000.0, 5.0), (-1000.0, 5.0), 10.0).unwrap();
+        let x = 1000.0 - (2000.0 * t);
+        assert!((((x * x) + 25.0).sqrt() - 10.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn circles_that_touch_at_the_start_hit_at_0() {
+        assert_eq!(sweep_circles((0.0, 0.0), (0.0, 0.0), (5.0, 0.0), (500.0, 0.0), 10.0), Some(0.0));
+        assert_eq!(sweep_circles((0.0, 0.0), (0.0, 0.0), (5.0, 0.0), (5.0, 0.0), 10.0), Some(0.0));
+    }
+
+    #[test]
+    fn near_misses_and_short_steps_dont_hit() {
+        // passes just outside
+        assert_eq!(sweep_circles((-1000.0, 0.0), (1000.0, 0.0), (0.0, 10.5), (0.0, 10.5), 10.0), None);
+        // stops short
+        assert_eq!(sweep_circles((-1000.0, 0.0), (-20.0, 0.0), (0.0, 0.0), (0.0, 0.0), 10.0), None);
+        // moving apart
+        assert_eq!(sweep_circles((-20.0, 0.0), (-1000.0, 0.0), (0.0, 0.0), (0.0, 0.0), 10.0), None);
+        // moving together side by side
+        assert_eq!(sweep_circles((0.0, 0.0), (100.0, 0.0), (0.0, 50.0), (100.0, 50.0), 10.0), None);
+    }
+}
@@ -0,0 +1,213 @@
+use serde::Deserialize;
+use crate::world::SIM_DT;
+use crate::util::bad_data;
+
+// gameplay numbers that used to be constants, read from resources/tuning.toml
+// anything left out of the file keeps its default, and the game watches the file so edits
+// apply while playing
+//
+// what each entity is made of lives in the prefabs instead, the player's thrust and fuel, star gravity,
+// turret fire rates and the fuel in a pickup among them, and those are copied into an entity when it
+// spawns, so an edit to one only shows up on the next ship, zone or shot, not on what's already out there
+
+#[derive(Deserialize, Clone, Debug)]
+#[serde(default, deny_unknown_fields)]
+pub struct Tuning {
+    // explosions, seconds per unit of size spent growing, staying and fading
+    pub explosion_grow: f64,
+    pub explosion_stay: f64,
+    pub explosion_fade: f64,
+
+    pub trail_len: usize, // points kept in a trail
+    pub trail_dist: f32, // how far something moves before its trail gets a new point
+
+    pub player_nuke_vel: f64, // launch speed on top of the ship's, for enemy ships too
+    pub player_nuke_dist: f64, // how far in front of the ship's edge it appears
+
+    pub pred_color: [f32; 4],
+    pub pred_rate: f64, // seconds between recalculating, 0 for every step
+    pub pred_tstep: f64, // seconds between predicted points, a whole number of SIM_DT so it lines up with real steps
+    pub pred_len: usize, // predicted points
+
+    pub turret_update_rate: f64, // seconds between turrets looking for the player
+    pub turret_range: f64,
+    pub turret_nuke_vel: f64,
+    pub turret_nuke_dist: f64, // past the turret's edge
+
+    pub shock_reach: f64, // how far out an explosion's shockwave goes, times its size
+    pub shock_push: f64, // speed it gives something right in the middle, per unit of size
+
+    pub hull_regen: f64, // hull a ship gets back a second
+    pub hull_explosion: f64, // how big a ship goes up when its hull gives out
+
+    pub respawn_delay: f64, // seconds watching the ship blow up before the next one or the game over screen
+
+    pub score_lock: f64,
+    pub score_turret: f64,
+    pub score_ship: f64,
+    pub score_boss: f64,
+    pub score_combo: f64, // extra for each kill in an explosion chain, times the kills before it
+    pub score_zone: f64, // for flying out of a zone, times how many zones deep it is
+    pub score_par_time: f64, // seconds, clearing a zone quicker than this scores the difference
+    pub score_time: f64, // per second under par
+    pub score_fuel: f64, // per unit of fuel left when the zone is cleared
+}
+
+impl Default for Tuning {
+    fn default() -> Tuning {
+        Tuning{
+            explosion_grow: 0.0018,
+            explosion_stay: 0.0012,
+            explosion_fade: 0.0009,
+
+            trail_len: 120,
+            trail_dist: 30.0,
+
+            player_nuke_vel: 750.0,
+            player_nuke_dist: 18.0,
+
+            pred_color: [0.6, 0.75, 1.0, 0.69],
+            pred_rate: 0.0,
+            pred_tstep: 0.25,
+            pred_len: 45,
+
+            turret_update_rate: 0.45,
+            turret_range: 8000.0,
+            turret_nuke_vel: 300.0,
+            turret_nuke_dist: 30.0,
+
+            shock_reach: 2.0,
+            shock_push: 0.3,
+
+            hull_regen: 8.0,
+            hull_explosion: 600.0,
+
+            respawn_delay: 2.5,
+
+            score_lock: 500.0,
+            score_turret: 250.0,
+            score_ship: 400.0,
+            score_boss: 3000.0,
+            score_combo: 200.0,
+            score_zone: 1000.0,
+            score_par_time: 90.0,
+            score_time: 10.0,
+            score_fuel: 1.0,
+        }
+    }
+}
+
+impl Tuning {
+    pub fn parse(text: &str) -> std::io::Result<Tuning> {
+        let t: Tuning = toml::from_str(text).map_err(|e| bad_data(format!("tuning: {}", e)))?;
+        t.validate().map_err(|e| bad_data(format!("tuning: {}", e)))?;
+        return Ok(t);
+    }
+
+    pub fn load(path: &std::path::Path) -> std::io::Result<Tuning> {
+        return Tuning::parse(&std::fs::read_to_string(path)?);
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        let positive = [
+            ("explosion_grow", self.explosion_grow),
+            ("explosion_stay", self.explosion_stay),
+            ("explosion_fade", self.explosion_fade),
+            ("trail_dist", self.trail_dist as f64),
+            ("pred_tstep", self.pred_tstep),
+            ("turret_update_rate", self.turret_update_rate),
+            ("turret_range", self.turret_range),
+        ];
+        for (name, v) in positive.iter() {
+            if !(*v > 0.0) || !v.is_finite() {
+                return Err(format!("{} is {}, it has to be above 0", name, v));
+            }
+        }
+
+        let not_negative = [
+            ("player_nuke_vel", self.player_nuke_vel),
+            ("player_nuke_dist", self.player_nuke_dist),
+            ("pred_rate", self.pred_rate),
+            ("turret_nuke_vel", self.turret_nuke_vel),
+            ("turret_nuke_dist", self.turret_nuke_dist),
+            ("shock_reach", self.shock_reach),
+            ("shock_push", self.shock_push),
+            ("hull_regen", self.hull_regen),
+            ("hull_explosion", self.hull_explosion),
+            ("respawn_delay", self.respawn_delay),
+            ("score_lock", self.score_lock),
+            ("score_turret", self.score_turret),
+            ("score_ship", self.score_ship),
+            ("score_boss", self.score_boss),
+            ("score_combo", self.score_combo),
+            ("score_zone", self.score_zone),
+            ("score_par_time", self.score_par_time),
+            ("score_time", self.score_time),
+            ("score_fuel", self.score_fuel),
+        ];
+        for (name, v) in not_negative.iter() {
+            if !(*v >= 0.0) || !v.is_finite() {
+                return Err(format!("{} is {}, it can't be below 0", name, v));
+            }
+        }
+
+        let counts = [
+            ("trail_len", self.trail_len, 3, 10000),
+            ("pred_len", self.pred_len, 2, 10000),
+        ];
+        for (name, v, lo, hi) in counts.iter() {
+            if v < lo || v > hi {
+                return Err(format!("{} is {}, it has to be from {} to {}", name, v, lo, hi));
+            }
+        }
+
+        let steps = self.pred_tstep / SIM_DT;
+        if (steps - steps.round()).abs() > 1e-6 {
+            return Err(format!("pred_tstep is {}, it has to be a whole number of steps of {}", self.pred_tstep, SIM_DT));
+        }
+
+        for c in self.pred_color.iter() {
+            if !(*c >= 0.0 && *c <= 1.0) {
+                return Err(format!("pred_color has {}, colors go from 0 to 1", c));
+            }
+        }
+
+        return Ok(());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn defaults_and_the_shipped_file_are_accepted() {
+        assert!(Tuning::default().validate().is_ok());
+        assert!(Tuning::parse(include_str!("../resources/tuning.toml")).is_ok());
+        assert!(Tuning::parse("").is_ok());
+    }
+
+    #[test]
+    fn out_of_range_values_are_rejected() {
+        let bad = [
+            "explosion_grow = 0.0",
+            "turret_range = -1.0",
+            "shock_push = -0.5",
+            "score_lock = nan",
+            "trail_len = 2",
+            "pred_len = 100000",
+            "pred_color = [0.5, 0.5, 1.5, 1.0]",
+            "no_such_number = 1.0",
+        ];
+        for text in bad.iter() {
+            assert!(Tuning::parse(text).is_err(), "{} was let through", text);
+        }
+    }
+
+    #[test]
+    fn pred_tstep_has_to_be_whole_steps() {
+        assert!(Tuning::parse(&format!("pred_tstep = {}", SIM_DT * 7.0)).is_ok());
+        assert!(Tuning::parse(&format!("pred_tstep = {}", SIM_DT * 7.5)).is_err());
+        assert!(Tuning::parse("pred_tstep = 0.105").is_err());
+    }
+}
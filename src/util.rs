@@ -0,0 +1,33 @@
+// small things more than one module needs
+
+// the error for a data file that was read but can't be used
+pub(crate) fn bad_data(msg: String) -> std::io::Error {
+    return std::io::Error::new(std::io::ErrorKind::InvalidData, msg);
+}
+
+// one whitespace separated field of a text file, None if it's missing or doesn't parse
+pub(crate) fn parse_field<T: std::str::FromStr>(f: Option<&str>) -> Option<T> {
+    return f?.parse().ok();
+}
+
+// fnv-1a over the text of the data files, so a replay or save can tell it was made with other ones
+// std's hasher isn't promised to give the same answer from one rust version to the next
+pub struct TextHash(u64);
+
+impl TextHash {
+    pub fn new() -> TextHash {
+        return TextHash(0xcbf29ce484222325);
+    }
+
+    // the name goes in too, so moving text from one file to another changes the hash
+    pub fn add(&mut self, name: &str, text: &str) {
+        for b in name.bytes().chain(std::iter::once(0)).chain(text.bytes()).chain(std::iter::once(0)) {
+            self.0 ^= b as u64;
+            self.0 = self.0.wrapping_mul(0x100000001b3);
+        }
+    }
+
+    pub fn finish(&self) -> u64 {
+        return self.0;
+    }
+}
@@ -0,0 +1,2395 @@
+use std::collections::BTreeMap;
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use std::io::{BufReader, BufRead};
+use crate::integrate::{Integrator, integrate};
+use crate::spatial::{Grid, GRID_CELL, sweep_circles, sweep_bounds};
+use crate::barnes_hut::{BhTree, BH_THETA};
+use crate::entity::Entities;
+use crate::rng::SimRng;
+use crate::prefab::{Prefabs, Overrides, Role, PAction, PTurretKind};
+use crate::tuning::Tuning;
+use crate::difficulty::{Difficulties, Difficulty, Preset};
+use crate::level::LevelFile;
+use crate::score::Score;
+use crate::levelgen::{GenError, GenFail, GEN_LAYOUTS, GEN_PLACE_TRIES, check_reach, place_fallback, LevelGenerator, Palette, generator_for};
+
+// the simulation side of the game
+// nothing in here touches ggez, so a World can be built and stepped headless
+
+// constants
+
+pub const PORTAL_SCALE: f32 = 300.0;
+pub const SHIP_SCALE: f32 = 18.0;
+pub const TURRET_SCALE: f32 = 120.0;
+pub const POWERUP_SCALE: f32 = 100.0;
+pub const CAPITAL_SCALE: f32 = 70.0;
+pub const SIM_DT: f64 = 1.0 / 120.0; // fixed physics step
+pub const LEAD_DT: f64 = 0.05; // step for following the player ahead when leading a shot
+pub const LEAD_TIME: f64 = 12.0; // longest flight a turret will lead for
+pub const LEAD_MIN_CLOSING: f64 = 100.0; // rockets falling behind still steer as if they were gaining this fast
+pub const NAV_GAIN: f64 = 3.0; // proportional navigation constant for rockets that don't give one
+pub const SHIP_HULL: f64 = 100.0; // for ships that don't give one
+pub const MOUNT_GAP: f64 = 10.0; // between a moon and the turret on it
+pub const MINE_TRIES: usize = 8; // spots tried round a mine layer for each mine
+pub const PILOT_ARRIVE: f64 = 250.0; // a patrolling ship this close to its waypoint picks another
+pub const PILOT_SLACK: f64 = 20.0; // how far off the velocity it wants a ship gets before it thrusts
+pub const PILOT_GRAV_LEAD: f64 = 0.5; // seconds of gravity a ship allows for when working out its thrust
+
+// file, scale, color for each MeshNum
+pub const MESHES: [(&str, f32, [f32; 4]); 15] = [
+    ("\\ang.obj", SHIP_SCALE, [1.0; 4]),
+    ("\\A.obj", SHIP_SCALE, [0.95, 0.5, 0.6, 1.0]),
+    ("\\ast.obj", POWERUP_SCALE, [0.75, 0.75, 0.81, 1.0]),
+    ("\\bangv.obj", POWERUP_SCALE, [0.75, 0.75, 1.0, 1.0]),
+    ("\\capital.obj", CAPITAL_SCALE, [0.85, 0.55, 0.95, 1.0]),
+    ("\\pnd.obj", TURRET_SCALE, [0.9, 0.48, 0.45, 1.0]),
+    ("\\nuke.obj", SHIP_SCALE, [0.81, 0.3, 0.3, 1.0]),
+    ("\\lock.obj", POWERUP_SCALE, [0.5, 0.5, 0.69, 1.0]),
+    ("\\ClosedPortal.obj", PORTAL_SCALE, [0.6, 0.6, 0.81, 1.0]),
+    ("\\OpenPortal.obj", PORTAL_SCALE, [0.3, 0.42, 0.9, 1.0]),
+    ("\\beam.obj", TURRET_SCALE, [0.95, 0.85, 0.4, 1.0]),
+    ("\\mines.obj", TURRET_SCALE, [0.7, 0.9, 0.45, 1.0]),
+    ("\\flak.obj", TURRET_SCALE, [0.95, 0.6, 0.3, 1.0]),
+    ("\\mine.obj", SHIP_SCALE, [0.8, 0.9, 0.5, 1.0]),
+    ("\\shell.obj", SHIP_SCALE, [1.0, 0.7, 0.4, 1.0]),
+];
+
+pub use crate::entity::IdVal;
+
+// component maps go in id order, so the simulation steps the same every run
+// and however the entities got there, spawned one by one or restored from a save
+pub type CMap<T> = BTreeMap<IdVal, T>;
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct CPos {
+    pub x: f64,
+    pub y: f64,
+    pub a: f32,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct CGrav {
+    pub mass: f64,
+    pub dist2: f64,  // distance squared at which this object can be ignored, when not using barnes_hut
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct CDynamic {
+    pub x_vel: f64,
+    pub y_vel: f64,
+    //a_vel: f32,
+    pub in_ax: f64,
+    pub in_ay: f64,
+    pub still: bool, // stays put until a shockwave knocks it loose
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct CTrail {
+    pub objid: IdVal,
+    pub pts: Vec<[f32; 2]>, // x, y, thrust
+    pub max_len: usize,
+    pub size: f32, // width of the trail
+    pub color: [f32; 4],
+    pub dist: f32,
+}
+
+// Used for predictions on movement
+#[derive(Serialize, Deserialize, Clone)]
+pub struct CPredictable {
+    pub objid: IdVal,
+    pub pts: Vec<[f32; 2]>,
+    pub tstep: f64,
+    pub rate: f64,
+    pub till_next: f64,
+    pub valid_len: usize,
+    pub collidable: bool,
+    pub color: [f32; 4],
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(tag = "kind", content = "args")]
+pub enum CollisionType {
+    Explosion(f32, bool),
+    FuelPup(f64),
+    Portal,
+    None,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct CCollider {
+    pub rad: f64,
+    pub col_action: CollisionType,
+    pub stop_col: bool,
+}
+
+pub struct RayHit {
+    pub id: IdVal,
+    pub dist: f64, // from the start of the ray
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct CCollides {
+    pub rad: f64,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct CExplosion {
+    pub grow_size: f32,
+    pub time_grow: f64,
+    pub time_stay: f64,
+    pub time_fade: f64,
+    pub time_so_far: f64,
+    pub chain: u32, // explosions set off by this one carry it on, so kills can be counted together
+    pub front: f64, // how far out the shockwave has got
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct CRocket {
+    pub thrust: f64,
+    pub target: Option<IdVal>,
+    pub lead: f64, // the skill of whatever fired it, 0 chases the target and 1 navigates to meet it
+    pub nav_gain: f64,
+    pub turn_rate: f64, // radians a second
+    pub burn: f64, // seconds of thrust left, then it coasts
+    pub fuze: f64, // goes off this close to its target
+}
+
+// what a turret does when it gets a shot off
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(tag = "kind", content = "args")]
+pub enum TurretKind {
+    Nuke, // fires its shot at the player and lets it home in
+    Flak(f64), // fires its shot at this speed, fuzed to burst where it meets the player
+    Mines(usize, f64), // drops its shot up to this far round itself, no more than so many out at once
+    Beam(f64, f64, f64), // damage a second, seconds it stays on for each shot, how far it reaches
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct CTurret {
+    pub name: String, // prefab it was made from, for saving the layout
+    pub kind: TurretKind,
+    pub shot: String, // prefab it fires or lays
+    pub fire_rate: f64,
+    pub till_next_shot: f64,
+    pub skill: f64, // 0 shoots at where the player is, 1 at where they'll be
+    pub beam: f64, // seconds the beam has left
+    pub beam_to: Option<(f64, f64)>, // where the beam ends while it's on
+    pub laid: Vec<IdVal>, // mines it has out
+}
+
+// goes off after going far enough, or with the player close enough
+#[derive(Serialize, Deserialize, Clone)]
+pub struct CFuze {
+    pub travel: f64, // left to go
+    pub near: f64,
+    pub arm: f64, // seconds before near counts
+}
+
+// goes round a point at a steady rate instead of falling
+#[derive(Serialize, Deserialize, Clone)]
+pub struct COrbit {
+    pub x: f64,
+    pub y: f64,
+    pub dist: f64,
+    pub rate: f64, // radians a second
+    pub angle: f64,
+    pub moon: Option<IdVal>, // what a turret is mounted on, it goes round with it
+}
+
+// where the player's ship was lost, the camera watches it go up before the next one
+#[derive(Serialize, Deserialize, Clone)]
+pub struct Death {
+    pub x: f64,
+    pub y: f64,
+    pub time: f64, // since it happened
+}
+
+// what the renderer should draw for an entity, the meshes themselves live in the renderer
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(tag = "kind", content = "args")]
+pub enum DrawThing {
+    Blank,
+    Circle(f32), // filled circle with this radius
+    Ring(f32, f32), // explosion ring, full size and current scale
+    Path, // the pts of a CTrail or CPredictable on the same entity
+    MeshInd(usize),
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct CDrawable {
+    pub thing: DrawThing,
+    pub r: f32, // radius for culling
+    pub minsz: f32,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct CShip {
+    pub thrust: f64, // fake thrust actually, pure accelaration, no mass
+    pub empty_thrust: f64, // small amount of thrust when no fuel is available
+    pub fuel: f64,
+    pub ammo: usize,
+    pub hull: f64, // beams wear this down, it goes at 0
+    pub max_hull: f64,
+    pub blast: f64, // hull lost a second inside an explosion, 0 goes up at once
+    pub shot: String, // prefab it fires
+}
+
+// what a ship does for a step, the player's input and pilots both fly through World::fly_ship
+pub struct ShipCmd {
+    pub aim: f32, // angle to face, thrust and shots go out the other way
+    pub thrust: bool,
+    pub fire: bool,
+    pub target: Option<IdVal>, // for the shot to home in on
+    pub lead: f64, // how well the shot leads its target
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Debug)]
+#[serde(rename_all = "lowercase")]
+pub enum PilotMode {
+    Patrol,
+    Pursue,
+    Evade, // about to hit a star
+    Flee, // low on fuel
+}
+
+// flies an enemy ship, see s_pilot
+#[derive(Serialize, Deserialize, Clone)]
+pub struct CPilot {
+    pub name: String, // prefab it was made from, for saving the layout
+    pub mode: PilotMode,
+    pub home: (f64, f64), // where it patrols round
+    pub waypoint: (f64, f64),
+    pub patrol: f64, // how far from home it goes
+    pub speed: f64, // it doesn't try to go faster than this
+    pub cruise: f64, // speed it patrols at
+    pub sight: f64, // notices the player this close with nothing solid between
+    pub range: f64, // fires from this close
+    pub keep: f64, // hangs back this far from the player
+    pub fire_rate: f64, // seconds between shots
+    pub till_next_shot: f64,
+    pub skill: f64, // passed on to what it fires
+    pub flee_fuel: f64, // runs for it with less fuel than this
+    pub look: f64, // seconds ahead it watches for stars
+    pub boss: bool,
+}
+
+pub struct InputState {
+    pub up: bool,
+    pub down: bool,
+    pub right: bool,
+    pub left: bool,
+    pub cw: bool,
+    pub ccw: bool,
+    pub reset: bool, // start a new run
+    pub cont: bool, // carry on from the zone reached after a game over
+
+    // mouse position in world space
+    pub mx: f64,
+    pub my: f64,
+    pub lmb: bool,
+    pub rmb: bool,
+}
+
+pub enum MeshNum {
+    AngMesh = 0,
+    AMesh,
+    AstMesh,
+    BangVMesh,
+    CapMesh,
+    HashMesh,
+    NukeMesh,
+    LockMesh,
+    ClosedMesh,
+    OpenMesh,
+    BeamMesh,
+    MinesMesh,
+    FlakMesh,
+    MineMesh,
+    ShellMesh,
+}
+
+pub struct MeshVert {
+    pub pos: [f32; 2],
+    pub uv: [f32; 2],
+    pub color: [f32; 4],
+}
+
+pub struct MeshData {
+    pub verts: Vec<MeshVert>,
+    pub inds: Vec<u32>,
+    pub rad: f32,
+}
+
+// where gravity comes from for a step, the barnes-hut tree if there is one
+// otherwise each source out to its dist2, found through the grid if there is one
+pub struct GravField<'a> {
+    pub gravs: &'a CMap<CGrav>,
+    pub pos: &'a CMap<CPos>,
+    pub grid: Option<&'a Grid>,
+    pub tree: Option<&'a BhTree>,
+}
+
+impl<'a> GravField<'a> {
+    pub fn accel(&self, x: f64, y: f64, id: &IdVal) -> (f64, f64) {
+        let (ax, ay, _) = match self.tree {
+            Some(t) => t.accel(x, y, id),
+            None => World::get_grav_a(self.gravs, self.pos, self.grid, x, y, id),
+        };
+        return (ax, ay);
+    }
+}
+
+pub struct World {
+    pub mesh_r: Vec<f32>, // radius of each MeshNum
+    pub rng: SimRng,
+    pub seed: u64, // seed for the whole run, each level gets its own from this
+    pub integrator: Integrator,
+    pub level: usize,
+    pub entities: Entities,
+    pub prefabs: Prefabs,
+    pub tuning: Tuning,
+    pub difficulties: Difficulties,
+    pub data_hash: u64, // of the data files the three above were loaded from, 0 for the builtin ones
+    pub preset: Preset, // which of the difficulties is being played
+    pub c_pos: CMap<CPos>,
+    pub prev_pos: CMap<(f64, f64, f32)>, // c_pos as it was before the last step, for interpolating
+    pub c_grav: CMap<CGrav>,
+    pub c_dynamic: CMap<CDynamic>,
+    pub c_collider: CMap<CCollider>,
+    pub c_collides: CMap<CCollides>,
+    pub c_drawable: CMap<CDrawable>,
+    pub c_trail: CMap<CTrail>,
+    pub c_predictable: CMap<CPredictable>,
+    pub c_ship: CMap<CShip>,
+    pub c_explosion: CMap<CExplosion>,
+    pub c_rocket: CMap<CRocket>,
+    pub c_turret: CMap<CTurret>,
+    pub c_fuze: CMap<CFuze>,
+    pub c_orbit: CMap<COrbit>,
+    pub c_pilot: CMap<CPilot>,
+
+    pub locks: Vec<IdVal>,
+    pub portal: Option<IdVal>,
+
+    pub s_turret_next: f64,
+
+    // rebuilt every step, gravity sources by their reach and colliders by their radius
+    pub grav_grid: Grid,
+    pub col_grid: Grid,
+    pub use_grid: bool, // false to check everything against everything, for comparing
+
+    // every source pulls through the tree instead of being cut off at its dist2
+    pub grav_tree: BhTree,
+    pub barnes_hut: bool,
+
+    pub levels: Vec<LevelFile>, // zones played from a file, the rest are generated
+    pub spawn: (f64, f64), // where the player started the zone
+    pub gen_error: Option<GenError>, // set when the generated zone might not be solvable
+    pub theme: &'static str, // name of the generator that made the zone
+    pub palette: Palette,
+
+    pub input: InputState,
+    pub playerid: Option<IdVal>,
+    pub finished: bool, // finished level
+    pub started: bool,
+    pub lives: Option<usize>, // ships left including the one flying, None for as many as it takes
+    pub death: Option<Death>,
+    pub game_over: bool, // out of ships, waiting to restart or continue
+
+    pub score: Score,
+    pub ended_runs: Vec<Score>, // runs that were lost or given up on, for the renderer to take into the high scores
+    pub next_chain: u32,
+}
+
+impl World {
+    pub fn new(mesh_r: Vec<f32>, seed: u64) -> World {
+        World{
+            mesh_r,
+            rng: SimRng::new(seed),
+            seed,
+            integrator: Integrator::Verlet,
+
+            level: 0,
+
+            input: InputState{
+                up: false,
+                down: false,
+                left: false,
+                right: false,
+                cw: false,
+                ccw: false,
+                reset: false,
+                cont: false,
+                mx: 0.0,
+                my: 0.0,
+                lmb: false,
+                rmb: false,
+            },
+
+            // items that change between levels /etc
+            entities: Entities::new(),
+            prefabs: Prefabs::builtin(),
+            tuning: Tuning::default(),
+            difficulties: Difficulties::builtin(),
+            data_hash: 0,
+            preset: Preset::Normal,
+            c_pos: CMap::default(),
+            prev_pos: CMap::default(),
+            c_grav: CMap::default(),
+            c_dynamic: CMap::default(),
+            c_collider: CMap::default(),
+            c_collides: CMap::default(),
+            c_drawable: CMap::default(),
+            c_trail: CMap::default(),
+            c_predictable: CMap::default(),
+            c_ship: CMap::default(),
+            c_explosion: CMap::default(),
+            c_rocket: CMap::default(),
+            c_turret: CMap::default(),
+            c_fuze: CMap::default(),
+            c_orbit: CMap::default(),
+            c_pilot: CMap::default(),
+
+            locks: Vec::new(),
+            portal: None,
+
+            s_turret_next: 0.0,
+
+            grav_grid: Grid::new(GRID_CELL),
+            col_grid: Grid::new(GRID_CELL),
+            use_grid: true,
+
+            grav_tree: BhTree::new(BH_THETA),
+            barnes_hut: true,
+
+            levels: Vec::new(),
+            spawn: (0.0, 0.0),
+            gen_error: None,
+            theme: "",
+            palette: Palette::default(),
+
+            playerid: None,
+            finished: false,
+            started: false,
+            lives: None,
+            death: None,
+            game_over: false,
+
+            score: Score::new(seed, Preset::Normal),
+            ended_runs: Vec::new(),
+            next_chain: 0,
+        }
+    }
+
+    // load just the collision radii of the meshes, for running without a renderer
+    pub fn load_mesh_r(dir: &std::path::Path) -> std::io::Result<Vec<f32>> {
+        let mut mesh_r = Vec::new();
+        for (p, scale, color) in MESHES.iter() {
+            let f = std::fs::File::open(dir.join(p.trim_start_matches('\\')))?;
+            mesh_r.push(parse_mesh(BufReader::new(f), *scale, *color).rad);
+        }
+
+        return Ok(mesh_r);
+    }
+
+    // swap in new tuning mid run, bringing the trails and predictions already out there up to date
+    pub fn retune(&mut self, t: Tuning) {
+        for (_, p) in &mut self.c_predictable {
+            p.pts.resize(t.pred_len, [0.0, 0.0]);
+            p.valid_len = p.valid_len.min(t.pred_len);
+            p.tstep = t.pred_tstep;
+            p.rate = t.pred_rate;
+            p.till_next = 0.0;
+            p.color = t.pred_color;
+        }
+        for (_, tr) in &mut self.c_trail {
+            tr.max_len = t.trail_len;
+            tr.pts.truncate(t.trail_len);
+            tr.dist = t.trail_dist;
+        }
+        self.tuning = t;
+    }
+
+    pub fn difficulty(&self) -> &Difficulty {
+        return self.difficulties.get(self.preset);
+    }
+
+    // advance the simulation by dt seconds, normally SIM_DT
+    pub fn step(&mut self, dt: f64) {
+        if self.finished {
+            self.score_zone();
+            self.start_level(self.level+1);
+        } else if self.input.reset {
+            if self.started && !self.game_over {
+                self.end_run();
+            }
+            self.new_run();
+            self.input.reset = false;
+        } else if self.input.cont && self.game_over {
+            // the zone reached starts over with a full set of ships
+            // but the score starts over, the one that got here is already in the table
+            self.lives = self.difficulty().lives;
+            self.score = Score::new(self.seed, self.preset);
+            self.start_level(self.level);
+        }
+        self.input.cont = false;
+        if !self.game_over {
+            self.score.zone_time += dt;
+        }
+
+        self.prev_pos.clear();
+        for (id, p) in &self.c_pos {
+            self.prev_pos.insert(*id, (p.x, p.y, p.a));
+        }
+
+        self.s_orbit(dt);
+        self.s_player(dt);
+        self.build_grav_grid();
+        self.s_pilot(dt);
+        self.s_move(dt);
+        self.build_col_grid();
+        self.s_collision(dt);
+        self.s_predict(dt);
+        self.s_trail(dt);
+        self.s_turret(dt);
+        self.s_rocket(dt);
+        self.s_fuze(dt);
+        self.s_explosion(dt);
+
+        self.s_destroy();
+        self.s_death(dt);
+    }
+
+    pub fn new_run(&mut self) {
+        self.lives = self.difficulty().lives;
+        self.score = Score::new(self.seed, self.preset);
+        self.start_level(0);
+    }
+
+    // hand the score over to ended_runs, when the ships run out or the run is abandoned
+    pub fn end_run(&mut self) {
+        let mut s = self.score.clone();
+        s.zone = self.level;
+        self.ended_runs.push(s);
+    }
+
+    // flying out of a zone scores its depth, how quickly it was done and the fuel left over
+    fn score_zone(&mut self) {
+        let t = &self.tuning;
+        let fuel = self.playerid.and_then(|pid| self.c_ship.get(&pid)).map_or(0.0, |s| s.fuel);
+        let pts = (t.score_zone * ((self.level + 1) as f64))
+            + (t.score_time * (t.score_par_time - self.score.zone_time).max(0.0))
+            + (t.score_fuel * fuel);
+        self.score.add(pts);
+        self.score.zones += 1;
+    }
+
+    // once the explosion has been watched for long enough the next ship comes in at the zone's spawn
+    // or the run is over
+    fn s_death(&mut self, dt: f64) {
+        let d = match self.death {
+            Some(ref mut d) if !self.game_over => d,
+            _ => return,
+        };
+        d.time += dt;
+        if d.time < self.tuning.respawn_delay {
+            return;
+        }
+
+        if self.lives == Some(0) {
+            self.game_over = true;
+            self.end_run();
+            return;
+        }
+        self.death = None;
+        self.spawn_player(self.spawn.0, self.spawn.1);
+    }
+
+    pub fn build_grav_grid(&mut self) {
+        if self.barnes_hut {
+            let pos = &self.c_pos;
+            self.grav_tree.build(self.c_grav.iter().map(
+                |(id, g)| (*id, pos[id].x, pos[id].y, g.mass)
+            ));
+            return;
+        }
+
+        self.grav_grid.clear();
+        for (id, g) in &self.c_grav {
+            let p = &self.c_pos[id];
+            self.grav_grid.insert(*id, p.x, p.y, g.dist2.sqrt());
+        }
+    }
+
+    // colliders go in by the circle around everywhere they were this step
+    pub fn build_col_grid(&mut self) {
+        self.col_grid.clear();
+        for (id, c) in &self.c_collider {
+            let (x0, y0, x1, y1) = World::sweep(&self.prev_pos, &self.c_pos, id);
+            let (mx, my, half) = sweep_bounds(x0, y0, x1, y1);
+            self.col_grid.insert(*id, mx, my, c.rad + half);
+        }
+    }
+
+    // where something started and ended this step, things made during the step didn't move
+    pub fn sweep(prev_pos: &CMap<(f64, f64, f32)>, pos: &CMap<CPos>, id: &IdVal) -> (f64, f64, f64, f64) {
+        let p = &pos[id];
+        match prev_pos.get(id) {
+            Some((px, py, _)) => return (*px, *py, p.x, p.y),
+            None => return (p.x, p.y, p.x, p.y),
+        }
+    }
+
+    // work out the predictions without moving anything, for the editor
+    pub fn preview(&mut self, dt: f64) {
+        self.prev_pos.clear();
+        for (id, p) in &self.c_pos {
+            self.prev_pos.insert(*id, (p.x, p.y, p.a));
+        }
+
+        self.build_grav_grid();
+        self.build_col_grid();
+        self.s_predict(dt);
+    }
+
+    // position between the last two steps, alpha 0 is the previous step and 1 the current
+    pub fn lerp_pos(&self, id: &IdVal, alpha: f64) -> (f64, f64, f32) {
+        let p = &self.c_pos[id];
+        let (px, py, pa) = match self.prev_pos.get(id) {
+            Some(pp) => *pp,
+            None => return (p.x, p.y, p.a), // new this step
+        };
+
+        // turn the short way round
+        let mut da = p.a - pa;
+        if da > std::f32::consts::PI {
+            da -= std::f32::consts::PI * 2.0;
+        } else if da < -std::f32::consts::PI {
+            da += std::f32::consts::PI * 2.0;
+        }
+
+        return (
+            px + ((p.x - px) * alpha),
+            py + ((p.y - py) * alpha),
+            pa + (da * (alpha as f32)),
+        );
+    }
+
+    pub fn reset(&mut self) {
+        // ids start over from the first slot too, so a level runs the same however the last one went,
+        // each a generation on so nothing held from the last one matches
+        self.entities.clear();
+        self.c_pos.clear();
+        self.prev_pos.clear();
+        self.c_grav.clear();
+        self.c_dynamic.clear();
+        self.c_collider.clear();
+        self.c_collides.clear();
+        self.c_drawable.clear();
+        self.c_trail.clear();
+        self.c_predictable.clear();
+        self.c_ship.clear();
+        self.c_explosion.clear();
+        self.c_rocket.clear();
+        self.c_turret.clear();
+        self.c_fuze.clear();
+        self.c_orbit.clear();
+        self.c_pilot.clear();
+        self.locks.clear();
+        self.portal = None;
+        self.s_turret_next = 0.0;
+        self.playerid = None;
+        self.level = 0;
+        self.finished = false;
+        self.started = true;
+        self.death = None;
+        self.game_over = false;
+        self.score.zone_time = 0.0;
+        self.score.chain_kills.clear();
+        self.next_chain = 0;
+    }
+
+    fn s_destroy(&mut self) {
+        for id in self.entities.take_marked() {
+            self.despawn(&id);
+        }
+    }
+
+    // the one place an entity and all its components go away
+    pub fn despawn(&mut self, id: &IdVal) {
+        if self.playerid == Some(*id) {
+            self.playerid = None;
+            let p = &self.c_pos[id];
+            self.death = Some(Death{x: p.x, y: p.y, time: 0.0});
+            self.lives = self.lives.map(|l| l.saturating_sub(1));
+        }
+
+        if let Some(i) = self.locks.iter().position(|l| l == id) {
+            self.locks.remove(i);
+            if self.locks.len() == 0 {
+                self.open_portal();
+            }
+        }
+        if self.portal == Some(*id) {
+            self.portal = None;
+        }
+
+        self.c_pos.remove(id);
+        self.prev_pos.remove(id);
+        self.c_grav.remove(id);
+        self.c_dynamic.remove(id);
+        self.c_collider.remove(id);
+        self.c_collides.remove(id);
+        self.c_drawable.remove(id);
+        self.c_predictable.remove(id);
+        self.c_ship.remove(id);
+        self.c_trail.remove(id);
+        self.c_explosion.remove(id);
+        self.c_rocket.remove(id);
+        self.c_turret.remove(id);
+        self.c_fuze.remove(id);
+        self.c_orbit.remove(id);
+        self.c_pilot.remove(id);
+
+        self.entities.free(id);
+    }
+
+    fn open_portal(&mut self) {
+        if let Some(portalid) = &self.portal {
+            let d = self.c_drawable.get_mut(portalid).unwrap();
+            let c = self.c_collider.get_mut(portalid).unwrap();
+
+            c.col_action = CollisionType::Portal;
+            d.thing = DrawThing::MeshInd(MeshNum::OpenMesh as usize);
+        }
+    }
+
+    // the zone's file if it has one, otherwise generate it
+    pub fn start_level(&mut self, level: usize) {
+        match self.levels.get(level).cloned() {
+            Some(l) => self.load_level(&l, level),
+            None => {
+                let _ = self.gen_level(level);
+            },
+        }
+    }
+
+    pub fn load_level(&mut self, l: &LevelFile, level: usize) {
+        self.reset();
+        self.level = level;
+        self.gen_error = None;
+        self.theme = "";
+        self.palette = Palette::default();
+        self.rng = SimRng::new(level_seed(self.seed, level));
+
+        self.add_portal(l.portal.x, l.portal.y, l.portal.a);
+        for s in &l.star {
+            self.add_star(s.x, s.y, s.size, s.always_pull);
+        }
+        for t in &l.turret {
+            match &t.orbit {
+                Some(o) => {
+                    let (dx, dy) = (t.x - o.x, t.y - o.y);
+                    self.add_orbit_turret(&t.prefab, o.x, o.y, ((dx * dx) + (dy * dy)).sqrt(), dy.atan2(dx), o.rate, o.moon);
+                },
+                None => {
+                    self.add_turret(&t.prefab, t.x, t.y, t.a);
+                },
+            }
+        }
+        for f in &l.fuel {
+            self.add_fuel_powerup(f.x, f.y);
+        }
+        for k in &l.lock {
+            self.add_lock(k.x, k.y, k.a);
+        }
+        self.spawn_player(l.spawn.x, l.spawn.y);
+        for s in &l.ship {
+            self.add_enemy(&s.prefab, s.x, s.y);
+        }
+        // nothing to unlock, it's open from the start
+        if self.locks.len() == 0 {
+            self.open_portal();
+        }
+
+        self.started = true;
+    }
+
+    // generate the zone with its generator, trying more layouts until one can be finished
+    // when none can the first one with everything in it is kept and the error says why,
+    // if none even had everything the zone is just the spawn and an open portal
+    pub fn gen_level(&mut self, level: usize) -> Result<(), GenError> {
+        let gen = generator_for(self.seed, level);
+        let base = level_seed(self.seed, level);
+        let mut kept = None;
+        let mut last = GenFail::NoRoom("spawn");
+        let mut res = Err(GenFail::NoRoom("spawn"));
+        for n in 0..GEN_LAYOUTS {
+            // the first layout is the one the seed always gave
+            let seed = if n == 0 { base } else { level_seed(base, n) };
+            res = self.gen_layout(gen, level, seed).and_then(|_| {
+                self.preview(SIM_DT);
+                check_reach(self)
+            });
+            match res {
+                Ok(()) => break,
+                Err(ref e) => {
+                    if kept.is_none() && !matches!(e, GenFail::NoRoom(_)) {
+                        kept = Some(seed);
+                    }
+                    last = e.clone();
+                },
+            }
+        }
+
+        self.theme = gen.name();
+        self.palette = gen.palette();
+        if res.is_ok() {
+            self.gen_error = None;
+            return Ok(());
+        }
+
+        match kept {
+            Some(seed) => {
+                let _ = self.gen_layout(gen, level, seed);
+            },
+            // every layout was missing its locks or spawn, an empty zone is better than no ship
+            None => {
+                self.reset();
+                self.level = level;
+                self.rng = SimRng::new(base);
+                self.started = true;
+                place_fallback(self);
+                self.open_portal();
+            },
+        }
+        let e = GenError{level, layouts: GEN_LAYOUTS, last};
+        self.gen_error = Some(e.clone());
+        return Err(e);
+    }
+
+    fn gen_layout(&mut self, gen: &dyn LevelGenerator, level: usize, seed: u64) -> Result<(), GenFail> {
+        self.reset();
+        self.level = level;
+        self.rng = SimRng::new(seed);
+        self.started = true;
+
+        return gen.layout(self, level);
+    }
+
+    // whether r fits at x, y without coming within gap of any collider
+    // one on an orbit takes up its whole ring
+    pub fn gen_fits(&self, x: f64, y: f64, r: f64, gap: f64) -> bool {
+        for (cid, c) in &self.c_collider {
+            let rdist = c.rad + r + gap;
+            if let Some(o) = self.c_orbit.get(cid) {
+                let d = ((o.x - x).powi(2) + (o.y - y).powi(2)).sqrt();
+                if (d - o.dist).abs() < rdist {
+                    return false;
+                }
+                continue;
+            }
+
+            let colpos = &self.c_pos.get(cid).unwrap();
+
+            let dx = colpos.x - x;
+            let dy = colpos.y - y;
+            if (rdist * rdist) > ((dx * dx) + (dy * dy)) {
+                // would overlap
+                return false;
+            }
+        }
+        return true;
+    }
+
+    // somewhere between dist from the middle and within the angles that r fits
+    // None once GEN_PLACE_TRIES spots have been taken
+    pub fn gen_spot(&mut self, dist: (f64, f64), angle: (f64, f64), r: f64, gap: f64) -> Option<(f64, f64)> {
+        for _ in 0..GEN_PLACE_TRIES {
+            let a = self.rng.gen_range(angle.0, angle.1);
+            let d = self.rng.gen_range(dist.0, dist.1);
+
+            let x = d * a.cos();
+            let y = d * a.sin();
+
+            if self.gen_fits(x, y, r, gap) {
+                return Some((x, y));
+            }
+        }
+        return None;
+    }
+
+    // the prefab makes it the player and puts a prediction on it
+    pub fn spawn_player(&mut self, x: f64, y: f64) -> IdVal {
+        self.spawn = (x, y);
+        return self.spawn_prefab("player", (x, y, 0.0), &Overrides::default()).expect("no player prefab");
+    }
+
+    pub fn make_player(&mut self, id: IdVal) {
+        self.playerid = Some(id);
+    }
+
+    pub fn add_entity(&mut self) -> IdVal {
+        return self.entities.spawn();
+    }
+
+    // build an entity from its prefab, None if there is no prefab by that name
+    pub fn spawn_prefab(&mut self, name: &str, pos: (f64, f64, f32), o: &Overrides) -> Option<IdVal> {
+        let p = self.prefabs.get(name)?.clone();
+
+        let id = self.add_entity();
+        let (x, y, a) = pos;
+        self.c_pos.insert(
+            id,
+            CPos{x, y, a},
+        );
+
+        // how big it is, radii default to this
+        let mut r = 0.0;
+        if let Some(m) = &p.mesh {
+            let i = mesh_index(m).unwrap();
+            r = self.mesh_r[i] as f64;
+            self.c_drawable.insert(
+                id,
+                CDrawable{
+                    thing: DrawThing::MeshInd(i),
+                    r: r as f32,
+                    minsz: p.minsz,
+                },
+            );
+        } else if let Some(size) = o.size.or(p.size) {
+            r = size;
+            self.c_drawable.insert(
+                id,
+                CDrawable{
+                    thing: DrawThing::Circle(size as f32),
+                    r: size as f32,
+                    minsz: p.minsz,
+                },
+            );
+        }
+
+        if let Some(g) = &p.grav {
+            let mass = match g.mass {
+                Some(m) => m,
+                None => g.mass_mul.unwrap() * r * r * r,
+            };
+            // reach = mass / dist2
+            let dist2 = match g.reach {
+                Some(reach) => mass / reach,
+                None => std::f64::INFINITY,
+            };
+            self.c_grav.insert(
+                id,
+                CGrav{mass, dist2},
+            );
+        }
+        if let Some(d) = &p.dynamic {
+            let (x_vel, y_vel) = o.vel.unwrap_or((d.vel[0], d.vel[1]));
+            self.c_dynamic.insert(
+                id,
+                CDynamic {
+                    x_vel,
+                    y_vel,
+                    in_ax: 0.0,
+                    in_ay: 0.0,
+                    still: d.still,
+                },
+            );
+        }
+        if let Some(c) = &p.collides {
+            self.c_collides.insert(
+                id,
+                CCollides{
+                    rad: c.rad.unwrap_or(r),
+                },
+            );
+        }
+        if let Some(c) = &p.collider {
+            let col_action = match c.action {
+                PAction::None => CollisionType::None,
+                PAction::Portal => CollisionType::Portal,
+                PAction::Fuel(amt) => CollisionType::FuelPup(amt),
+                PAction::Explosion{size, destroy_self} => CollisionType::Explosion(size, destroy_self),
+            };
+            self.c_collider.insert(
+                id,
+                CCollider{
+                    rad: c.rad.unwrap_or(r),
+                    col_action,
+                    stop_col: c.stop,
+                },
+            );
+        }
+        if let Some(s) = &p.ship {
+            self.c_ship.insert(
+                id,
+                CShip {
+                    thrust: s.thrust,
+                    empty_thrust: s.empty_thrust,
+                    fuel: s.fuel,
+                    ammo: s.ammo,
+                    hull: s.hull.unwrap_or(SHIP_HULL),
+                    max_hull: s.hull.unwrap_or(SHIP_HULL),
+                    blast: s.blast,
+                    shot: s.shot.clone().unwrap_or_else(|| "player_nuke".to_string()),
+                }
+            );
+        }
+        if let Some(pl) = &p.pilot {
+            let fuel = p.ship.as_ref().map_or(0.0, |s| s.fuel);
+            self.c_pilot.insert(
+                id,
+                CPilot{
+                    name: name.to_string(),
+                    mode: PilotMode::Patrol,
+                    home: (x, y),
+                    waypoint: (x, y),
+                    patrol: pl.patrol,
+                    speed: pl.speed,
+                    cruise: pl.cruise,
+                    sight: pl.sight,
+                    range: pl.range,
+                    keep: pl.keep,
+                    fire_rate: pl.fire_rate,
+                    till_next_shot: pl.fire_rate,
+                    skill: pl.skill.unwrap_or(1.0),
+                    flee_fuel: fuel * pl.flee_fuel,
+                    look: pl.look,
+                    boss: pl.boss,
+                },
+            );
+        }
+        if let Some(rk) = &p.rocket {
+            self.c_rocket.insert(
+                id,
+                CRocket{
+                    thrust: rk.thrust,
+                    target: o.target,
+                    lead: 0.0,
+                    nav_gain: rk.nav_gain.unwrap_or(NAV_GAIN),
+                    turn_rate: rk.turn_rate.unwrap_or(std::f64::INFINITY),
+                    burn: rk.burn.unwrap_or(std::f64::INFINITY),
+                    fuze: rk.fuze.unwrap_or(0.0),
+                }
+            );
+        }
+        if let Some(t) = &p.turret {
+            self.c_turret.insert(
+                id,
+                CTurret{
+                    name: name.to_string(),
+                    kind: match t.kind {
+                        PTurretKind::Nuke => TurretKind::Nuke,
+                        PTurretKind::Flak{speed} => TurretKind::Flak(speed),
+                        PTurretKind::Mines{count, spread} => TurretKind::Mines(count, spread),
+                        PTurretKind::Beam{dps, time, range} => TurretKind::Beam(dps, time, range),
+                    },
+                    shot: t.shot.clone().unwrap_or_else(|| "turret_nuke".to_string()),
+                    fire_rate: t.fire_rate,
+                    till_next_shot: 0.0,
+                    skill: t.skill.unwrap_or(1.0),
+                    beam: 0.0,
+                    beam_to: None,
+                    laid: Vec::new(),
+                },
+            );
+        }
+        if let Some(f) = &p.fuze {
+            self.c_fuze.insert(
+                id,
+                CFuze{
+                    travel: f.travel.unwrap_or(std::f64::INFINITY),
+                    near: f.near,
+                    arm: f.arm,
+                },
+            );
+        }
+
+        match p.role {
+            Some(Role::Lock) => self.locks.push(id),
+            Some(Role::Portal) => self.portal = Some(id),
+            Some(Role::Player) => self.make_player(id),
+            None => (),
+        }
+
+        // helper entities that follow this one
+        if let Some(t) = &p.trail {
+            self.add_trail(&id, t.size, t.color);
+        }
+        if let Some(pr) = &p.prediction {
+            self.add_prediction(id, pr.drawable);
+        }
+
+        return Some(id);
+    }
+
+    pub fn add_portal(&mut self, x: f64, y: f64, a: f32) -> IdVal {
+        return self.spawn_prefab("portal", (x, y, a), &Overrides::default()).expect("no portal prefab");
+    }
+
+    pub fn add_lock(&mut self, x: f64, y: f64, a: f32) -> IdVal {
+        return self.spawn_prefab("lock", (x, y, a), &Overrides::default()).expect("no lock prefab");
+    }
+
+    // any of the turret prefabs, None if there is no prefab by that name
+    pub fn add_turret(&mut self, name: &str, x: f64, y: f64, a: f32) -> Option<IdVal> {
+        return self.spawn_prefab(name, (x, y, a), &Overrides::default());
+    }
+
+    // a turret dist out from x, y going round at rate, standing on a moon of that size on the inside
+    pub fn add_orbit_turret(&mut self, name: &str, x: f64, y: f64, dist: f64, angle: f64, rate: f64, moon: f64) -> Option<IdVal> {
+        let (ux, uy) = (angle.cos(), angle.sin());
+        let id = self.add_turret(name, x + (ux * dist), y + (uy * dist), angle as f32)?;
+        let tr = self.c_collides.get(&id).map_or(0.0, |c| c.rad);
+        let md = dist - (tr + MOUNT_GAP + moon);
+        let mid = self.spawn_prefab("moon", (x + (ux * md), y + (uy * md), 0.0), &Overrides::size(moon)).expect("no moon prefab");
+
+        self.c_orbit.insert(mid, COrbit{x, y, dist: md, rate, angle, moon: None});
+        self.c_orbit.insert(id, COrbit{x, y, dist, rate, angle, moon: Some(mid)});
+        return Some(id);
+    }
+
+    // a ship flown by a pilot, patrolling round where it starts, None if there is no prefab by that name
+    pub fn add_enemy(&mut self, name: &str, x: f64, y: f64) -> Option<IdVal> {
+        return self.spawn_prefab(name, (x, y, 0.0), &Overrides::default());
+    }
+
+    pub fn add_fuel_powerup(&mut self, x: f64, y: f64) -> IdVal {
+        return self.spawn_prefab("fuel", (x, y, 0.0), &Overrides::default()).expect("no fuel prefab");
+    }
+
+    pub fn add_prediction(&mut self, objid: IdVal, drawable: bool) -> IdVal {
+        let p_id = self.add_entity();
+
+        let mut ptsvec = Vec::new();
+        //Add points
+        for _ in 0..self.tuning.pred_len {
+            ptsvec.push([0.0,0.0]);
+        }
+        self.c_predictable.insert(
+            p_id,
+            CPredictable{
+                objid,
+                pts: ptsvec,
+                tstep: self.tuning.pred_tstep,
+                rate: self.tuning.pred_rate,
+                till_next: 0.0,
+                valid_len: 0,
+                collidable: true,
+                color: self.tuning.pred_color,
+            },
+        );
+        if drawable {
+            self.c_drawable.insert(
+                p_id,
+                CDrawable{
+                    thing: DrawThing::Path,
+                    r: std::f32::INFINITY,
+                    minsz: 0.0, // we do this in the mesh gen
+                },
+            );
+        }
+        self.c_pos.insert(
+            p_id,
+            CPos{x: 0.0, y: 0.0, a: 0.0},
+        );
+
+        return p_id;
+    }
+
+    pub fn add_star(&mut self, x: f64, y: f64, size: f64, always_pull: bool) -> IdVal {
+        let name = if always_pull { "big_star" } else { "star" };
+        return self.spawn_prefab(name, (x, y, 0.0), &Overrides::size(size)).expect("no star prefab");
+    }
+
+    pub fn add_trail(&mut self, pid: &IdVal, size: f32, color: [f32; 4]) -> IdVal {
+        let id = self.add_entity();
+        self.c_trail.insert(
+            id,
+            CTrail{
+                objid: *pid,
+                pts: Vec::new(),
+                max_len: self.tuning.trail_len,
+                size,
+                color: color,
+                dist: self.tuning.trail_dist,
+            },
+        );
+        self.c_drawable.insert(
+            id,
+            CDrawable{
+                thing: DrawThing::Path,
+                r: std::f32::INFINITY,
+                minsz: 0.0,
+            },
+        );
+        self.c_pos.insert(
+            id,
+            CPos{x: 0.0, y: 0.0, a: 0.0},
+        );
+
+        return id;
+    }
+
+    pub fn spawn_explosion(&mut self, px: f64, py: f64, size: f32, collidable: bool) -> IdVal {
+        let id = self.add_entity();
+        self.c_pos.insert(
+            id,
+            CPos{x: px, y: py, a: 0.0},
+        );
+
+        let s64 = size as f64;
+        let tg = self.tuning.explosion_grow * s64;
+        let ts = tg + (self.tuning.explosion_stay * s64);
+        let tf = ts + (self.tuning.explosion_fade * s64);
+        self.c_explosion.insert(
+            id,
+            CExplosion{
+                grow_size: size,
+                time_grow: tg,
+                time_stay: ts,
+                time_fade: tf,
+                time_so_far: 0.0,
+                chain: 0,
+                front: 0.0,
+            },
+        );
+
+        self.c_drawable.insert(
+            id,
+            CDrawable{
+                thing: DrawThing::Ring(size, 0.0),
+                r: 30.0,
+                minsz: 0.0,
+            },
+        );
+
+        if collidable {
+            self.c_collider.insert(
+                id,
+                CCollider{
+                    rad: 0.0,
+                    col_action: CollisionType::Explosion(size/1.5, false),
+                    stop_col: false,
+                },
+            );
+        }
+
+        return id;
+    }
+
+    pub fn spawn_nuke(&mut self, name: &str, px: f64, py: f64, a: f32, vx: f64, vy: f64, target: Option<IdVal>) -> IdVal {
+        let o = Overrides{
+            vel: Some((vx, vy)),
+            target,
+            ..Default::default()
+        };
+        return self.spawn_prefab(name, (px, py, a), &o).expect("no nuke prefab");
+    }
+
+    // gravity at a point, only looking at the sources in grid if there is one
+    pub fn get_grav_a(gravs: &CMap<CGrav>, pos: &CMap<CPos>, grid: Option<&Grid>, px: f64, py: f64, id: &IdVal) -> (f64, f64, usize) {
+        let mut ax: f64 = 0.0;
+        let mut ay: f64 = 0.0;
+
+        let mut count = 0;
+
+        let mut add = |gid: &IdVal, g: &CGrav| {
+            if *gid == *id {
+                return;
+            }
+
+            let gp = &pos[gid];
+
+            let dx = gp.x - px;
+            let dy = gp.y - py;
+
+            if dx == 0.0 && dy == 0.0 {
+                return;
+            }
+
+            let r2 = (dx * dx) + (dy * dy);
+            if r2 > g.dist2 {
+                return;
+            }
+
+            count += 1;
+
+            let r = r2.sqrt();
+            let r3 = r2 * r;
+
+            // get accelaration due to this item
+            let ga = g.mass / r3;
+            ax += ga * dx;
+            ay += ga * dy;
+        };
+
+        match grid {
+            Some(grid) => {
+                for gid in grid.at(px, py) {
+                    if let Some(g) = gravs.get(gid) {
+                        add(gid, g);
+                    }
+                }
+            },
+            None => {
+                for (gid, g) in gravs {
+                    add(gid, g);
+                }
+            },
+        }
+
+        return (ax, ay, count);
+    }
+
+    // first collider the segment from 1 to 2 touches, of the ones filter lets block it
+    // a segment starting inside a collider hits it at distance 0
+    pub fn raycast<F: Fn(&IdVal, &CCollider) -> bool>(
+        colliders: &CMap<CCollider>,
+        pos: &CMap<CPos>,
+        grid: Option<&Grid>,
+        px1: f64, py1: f64,
+        px2: f64, py2: f64,
+        filter: F,
+    ) -> Option<RayHit> {
+        let mut cands = Vec::new();
+        match grid {
+            Some(grid) => grid.query_segment(px1, py1, px2, py2, &mut cands),
+            None => cands.extend(colliders.keys()),
+        }
+
+        let mut hit: Option<(f64, IdVal)> = None;
+        for cid in &cands {
+            let col = match colliders.get(cid) {
+                Some(col) => col,
+                None => continue,
+            };
+            if !filter(cid, col) {
+                continue;
+            }
+            // the segment is a point moving past a still circle
+            let cp = pos.get(cid).unwrap();
+            let c = (cp.x, cp.y);
+            if let Some(t) = sweep_circles(c, c, (px1, py1), (px2, py2), col.rad) {
+                let first = match hit {
+                    Some((ht, hid)) => t < ht || (t == ht && *cid < hid),
+                    None => true,
+                };
+                if first {
+                    hit = Some((t, *cid));
+                }
+            }
+        }
+
+        let dx = px2 - px1;
+        let dy = py2 - py1;
+        return hit.map(|(t, id)| RayHit{id, dist: t * ((dx * dx) + (dy * dy)).sqrt()});
+    }
+
+    // only solid things block line of sight, not pickups or explosions
+    pub fn blocks_sight(_id: &IdVal, col: &CCollider) -> bool {
+        return col.stop_col;
+    }
+
+    // where to aim a shot from s so it meets a target at t moving at tv, coasting under gravity
+    // the shot leaves at speed and speeds up at thrust for burn seconds in a straight line, the aim point
+    // is moved against how far gravity halfway there would pull it off over the flight
+    // None if it can't get there within LEAD_TIME
+    pub fn intercept(
+        field: &GravField, integ: Integrator,
+        s: (f64, f64), t: (f64, f64, f64, f64),
+        speed: f64, thrust: f64, burn: f64,
+    ) -> Option<(f64, f64)> {
+        let accel = |x: f64, y: f64| field.accel(x, y, &IdVal::NONE);
+        let (mut x, mut y, mut vx, mut vy) = t;
+        let mut time = 0.0;
+        while time <= LEAD_TIME {
+            let (gx, gy) = accel((s.0 + x) / 2.0, (s.1 + y) / 2.0);
+            let drop = 0.5 * time * time;
+            let (ax, ay) = (x - (gx * drop), y - (gy * drop));
+            let dist = ((ax - s.0).powi(2) + (ay - s.1).powi(2)).sqrt();
+            // once the burn is over it coasts at whatever it got up to
+            let reach = if time > burn {
+                (speed * time) + (thrust * burn * (time - (burn / 2.0)))
+            } else {
+                (speed * time) + (thrust * 0.5 * time * time)
+            };
+            if reach >= dist {
+                return Some((ax, ay));
+            }
+
+            let (nx, ny, nvx, nvy) = integrate(integ, &accel, x, y, vx, vy, 0.0, 0.0, LEAD_DT);
+            x = nx;
+            y = ny;
+            vx = nvx;
+            vy = nvy;
+            time += LEAD_DT;
+        }
+        return None;
+    }
+
+    fn s_turret(&mut self, mut dt: f64) {
+        self.s_turret_next += dt;
+        if self.s_turret_next > self.tuning.turret_update_rate {
+            dt = self.s_turret_next;
+            self.s_turret_next = 0.0;
+        } else {
+            return;
+        }
+
+        if self.playerid.is_none() {
+            for (_, t) in &mut self.c_turret {
+                t.beam = 0.0;
+                t.beam_to = None;
+            }
+            return;
+        }
+
+        let pid = self.playerid.unwrap();
+        let ppos = self.c_pos.get(&pid).unwrap();
+        let pdyn = self.c_dynamic.get(&pid).unwrap();
+        let col_grid = if self.use_grid { Some(&self.col_grid) } else { None };
+        let field = GravField{
+            gravs: &self.c_grav,
+            pos: &self.c_pos,
+            grid: if self.use_grid { Some(&self.grav_grid) } else { None },
+            tree: if self.barnes_hut { Some(&self.grav_tree) } else { None },
+        };
+
+        let fire_rate = self.difficulty().turret_fire_rate.at(self.level);
+        let skill = self.difficulty().turret_skill.at(self.level);
+        let thrust = self.difficulty().nuke_thrust.at(self.level);
+        let range = self.tuning.turret_range;
+        let mut shot = None; // (prefab, x, y, angle, x vel, y vel, lead, flak travel)
+        let mut lay = Vec::new(); // (turret, prefab, x, y, from, to)
+        let mut burn = 0.0; // beam damage to the player
+
+        for (id, t) in &mut self.c_turret {
+            t.till_next_shot -= dt;
+            let p = self.c_pos.get(id).unwrap();
+            let dx = p.x - ppos.x;
+            let dy = p.y - ppos.y;
+            let d2 = (dx*dx)+(dy*dy);
+            let rad = self.c_collides.get(id).map_or(0.0, |c| c.rad);
+
+            match t.kind {
+                TurretKind::Beam(dps, time, reach) => {
+                    if t.beam <= 0.0 && t.till_next_shot <= 0.0 && d2 < (reach * reach) {
+                        t.till_next_shot = t.fire_rate / fire_rate;
+                        t.beam = time;
+                    }
+                    if t.beam <= 0.0 {
+                        t.beam_to = None;
+                        continue;
+                    }
+                    t.beam -= dt;
+
+                    // straight at the player as far as it reaches, stopped by anything solid on the way
+                    let dist = d2.sqrt().max(1.0);
+                    let far = dist.min(reach);
+                    let (ex, ey) = (p.x - (dx * far / dist), p.y - (dy * far / dist));
+                    let hit = World::raycast(
+                        &self.c_collider, &self.c_pos, col_grid,
+                        p.x, p.y,
+                        ex, ey,
+                        World::blocks_sight,
+                    );
+                    match hit {
+                        Some(h) => t.beam_to = Some((p.x - (dx * h.dist / dist), p.y - (dy * h.dist / dist))),
+                        None => {
+                            t.beam_to = Some((ex, ey));
+                            if dist <= reach {
+                                burn += dps * fire_rate * dt;
+                            }
+                        },
+                    }
+                    continue;
+                },
+                TurretKind::Mines(count, spread) => {
+                    // mines it laid that went off don't count
+                    let entities = &self.entities;
+                    t.laid.retain(|m| entities.is_alive(m));
+                    if t.till_next_shot <= 0.0 && d2 < (range * range) && t.laid.len() < count {
+                        t.till_next_shot = t.fire_rate / fire_rate;
+                        lay.push((*id, t.shot.clone(), p.x, p.y, rad + self.tuning.turret_nuke_dist, rad + spread));
+                    }
+                    continue;
+                },
+                TurretKind::Nuke | TurretKind::Flak(_) => (),
+            }
+
+            if shot.is_some() || t.till_next_shot > 0.0 {
+                continue; // don't have to all fire at once
+            }
+            if d2 < (range * range) && World::raycast(
+                &self.c_collider, &self.c_pos, col_grid,
+                ppos.x, ppos.y,
+                p.x, p.y,
+                World::blocks_sight,
+            ).is_none() {
+                t.till_next_shot = t.fire_rate / fire_rate;
+
+                // a nuke leaves at turret_nuke_vel and speeds up until its burn runs out,
+                // flak goes at its own speed and doesn't
+                let (speed, accel, burn) = match t.kind {
+                    TurretKind::Flak(speed) => (speed, 0.0, 0.0),
+                    _ => {
+                        let rk = self.prefabs.get(&t.shot).and_then(|p| p.rocket.as_ref());
+                        let rt = rk.map_or(0.0, |r| r.thrust);
+                        let rb = rk.and_then(|r| r.burn).unwrap_or(std::f64::INFINITY);
+                        (self.tuning.turret_nuke_vel, rt * thrust, rb)
+                    },
+                };
+
+                // somewhere between where the player is and where the shot could meet them
+                let mut ax = ppos.x;
+                let mut ay = ppos.y;
+                let sk = (t.skill * skill).min(1.0);
+                if sk > 0.0 {
+                    let hit = World::intercept(
+                        &field, self.integrator,
+                        (p.x, p.y),
+                        (ppos.x, ppos.y, pdyn.x_vel, pdyn.y_vel),
+                        speed, accel, burn,
+                    );
+                    if let Some((ix, iy)) = hit {
+                        ax += (ix - ax) * sk;
+                        ay += (iy - ay) * sk;
+                    }
+                }
+
+                let na = (p.y - ay).atan2(p.x - ax) as f32;
+                let nac = -na.cos() as f64;
+                let nas = -na.sin() as f64;
+                // out past the turret's edge, or the shot takes it out on the way
+                let nd = rad + self.tuning.turret_nuke_dist;
+                let (npx, npy) = (p.x + (nac * nd), p.y + (nas * nd));
+                // flak bursts where it was aimed
+                let travel = ((ax - npx).powi(2) + (ay - npy).powi(2)).sqrt();
+                shot = Some((t.shot.clone(), npx, npy, na, nac * speed, nas * speed, sk, travel));
+            }
+        }
+
+        if let Some((name, npx, npy, na, nxv, nyv, lead, travel)) = shot {
+            let id = self.spawn_nuke(&name, npx, npy, na, nxv, nyv, Some(pid));
+            if let Some(r) = self.c_rocket.get_mut(&id) {
+                r.lead = lead;
+                r.thrust *= thrust;
+            }
+            if let Some(f) = self.c_fuze.get_mut(&id) {
+                if !f.travel.is_finite() {
+                    f.travel = travel;
+                }
+            }
+        }
+
+        for (tid, name, x, y, from, to) in lay {
+            let r = self.prefabs.get(&name).and_then(|p| p.mesh.as_ref()).and_then(|m| mesh_index(m)).map_or(0.0, |i| self.mesh_r[i] as f64);
+            for _ in 0..MINE_TRIES {
+                let a = self.rng.gen_range(0.0, std::f64::consts::PI * 2.0);
+                let d = self.rng.gen_range(from, to.max(from + 1.0));
+                let (mx, my) = (x + (a.cos() * d), y + (a.sin() * d));
+                if self.gen_fits(mx, my, r, 0.0) {
+                    if let Some(mid) = self.spawn_prefab(&name, (mx, my, a as f32), &Overrides::default()) {
+                        self.c_turret.get_mut(&tid).unwrap().laid.push(mid);
+                    }
+                    break;
+                }
+            }
+        }
+
+        // a ship the beams wear through goes up
+        if burn > 0.0 {
+            let s = self.c_ship.get_mut(&pid).unwrap();
+            s.hull -= burn;
+            if s.hull <= 0.0 && self.entities.destroy(&pid) {
+                let (x, y) = (self.c_pos[&pid].x, self.c_pos[&pid].y);
+                let eid = self.spawn_explosion(x, y, self.tuning.hull_explosion as f32, true);
+                self.next_chain += 1;
+                self.c_explosion.get_mut(&eid).unwrap().chain = self.next_chain;
+            }
+        }
+    }
+
+    // bodies on orbits go round, turrets face out from the middle
+    fn s_orbit(&mut self, dt: f64) {
+        for (id, o) in &mut self.c_orbit {
+            o.angle = (o.angle + (o.rate * dt)).rem_euclid(std::f64::consts::PI * 2.0);
+            let p = self.c_pos.get_mut(id).unwrap();
+            p.x = o.x + (o.angle.cos() * o.dist);
+            p.y = o.y + (o.angle.sin() * o.dist);
+            if o.moon.is_some() {
+                p.a = o.angle as f32;
+            }
+        }
+    }
+
+    // shells go off once they've gone far enough, and mines once armed when the player comes close
+    fn s_fuze(&mut self, dt: f64) {
+        let player = self.playerid.and_then(|id| self.c_pos.get(&id)).map(|p| (p.x, p.y));
+        let mut lit = Vec::new();
+        for (id, f) in &mut self.c_fuze {
+            let p = &self.c_pos[id];
+            if let Some((px, py, _)) = self.prev_pos.get(id) {
+                f.travel -= ((p.x - px).powi(2) + (p.y - py).powi(2)).sqrt();
+            }
+            f.arm -= dt;
+
+            let near = match player {
+                Some((px, py)) if f.arm <= 0.0 => ((p.x - px).powi(2) + (p.y - py).powi(2)) <= (f.near * f.near),
+                _ => false,
+            };
+            if f.travel <= 0.0 || near {
+                lit.push(*id);
+            }
+        }
+        for id in lit {
+            self.detonate(&id);
+        }
+    }
+
+    // blow something up where it is, like hitting something would, starting a new chain
+    fn detonate(&mut self, id: &IdVal) {
+        if !self.entities.destroy(id) {
+            return;
+        }
+        let sz = match self.c_collider.get(id).map(|c| &c.col_action) {
+            Some(CollisionType::Explosion(sz, _)) => *sz,
+            _ => return,
+        };
+        let (x, y) = (self.c_pos[id].x, self.c_pos[id].y);
+        let eid = self.spawn_explosion(x, y, sz, true);
+        self.next_chain += 1;
+        self.c_explosion.get_mut(&eid).unwrap().chain = self.next_chain;
+    }
+
+    fn s_explosion(&mut self, dt: f64) {
+        let mut shocks = Vec::new(); // (x, y, front before, front now, reach, size)
+        for (id, ex) in &mut self.c_explosion {
+            ex.time_so_far += dt;
+
+            // the shockwave grows with the explosion out to shock_reach times its size
+            let reach = (ex.grow_size as f64) * self.tuning.shock_reach;
+            let front = (ex.time_so_far / ex.time_grow).min(1.0).sqrt() * reach;
+            if front > ex.front {
+                let p = &self.c_pos[id];
+                shocks.push((p.x, p.y, ex.front, front, reach, ex.grow_size as f64));
+                ex.front = front;
+            }
+
+            let d = &mut self.c_drawable.get_mut(id).unwrap();
+
+            if ex.time_so_far <= ex.time_grow {
+                // grow
+                let mut r = ex.time_so_far / ex.time_grow;
+                r = r.sqrt();
+                if let DrawThing::Ring(_, ref mut sc) = d.thing {
+                    *sc = r as f32;
+                }
+                r *= ex.grow_size as f64;
+                d.r = r as f32;
+                if let Some(c) = &mut self.c_collider.get_mut(id) {
+                    c.rad = r;
+                }
+            } else if ex.time_so_far <= ex.time_stay {
+                continue;
+            } else if ex.time_so_far <= ex.time_fade{
+                // fade out?
+                let mut r = 1.0 - ((ex.time_so_far - ex.time_stay) / (ex.time_fade - ex.time_stay));
+                if let DrawThing::Ring(_, ref mut sc) = d.thing {
+                    *sc = r as f32;
+                }
+                r *= ex.grow_size as f64;
+                d.r = r as f32;
+                if let Some(c) = &mut self.c_collider.get_mut(id) {
+                    c.rad = r;
+                }
+            } else {
+                // remove this explosion
+                self.entities.destroy(id);
+            }
+        }
+
+        // everything the front went past this step gets pushed straight out from the middle,
+        // hardest close in and nothing at the edge of its reach, and anything still is knocked loose
+        let push = self.tuning.shock_push;
+        for (x, y, from, to, reach, size) in shocks {
+            for (id, d) in &mut self.c_dynamic {
+                let p = &self.c_pos[id];
+                let (dx, dy) = (p.x - x, p.y - y);
+                let dist = ((dx * dx) + (dy * dy)).sqrt();
+                if dist <= from || dist > to {
+                    continue;
+                }
+                let dv = push * size * (1.0 - (dist / reach));
+                d.x_vel += dx / dist * dv;
+                d.y_vel += dy / dist * dv;
+                d.still = false;
+            }
+        }
+    }
+
+    // point rockets at their targets by proportional navigation, turning no faster than they can,
+    // and thrust until the burn is used up, then they coast
+    fn s_rocket(&mut self, dt: f64) {
+        let mut fuzed = Vec::new();
+        for (id, r) in &mut self.c_rocket {
+            let mut want = None;
+            // target is probably destroyed, it carries on straight and still burns out
+            let c_pos = &self.c_pos;
+            if r.target.map_or(false, |tid| !c_pos.contains_key(&tid)) {
+                r.target = None;
+            }
+            if let Some(tid) = &r.target {
+                let tp = &self.c_pos[tid];
+                let p = &self.c_pos[id];
+                let d = &self.c_dynamic[id];
+                let (tvx, tvy) = self.c_dynamic.get(tid).map_or((0.0, 0.0), |td| (td.x_vel, td.y_vel));
+
+                let (rx, ry) = (tp.x - p.x, tp.y - p.y);
+                let (rvx, rvy) = (tvx - d.x_vel, tvy - d.y_vel);
+                let r2 = ((rx * rx) + (ry * ry)).max(1.0);
+                let dist = r2.sqrt();
+                if dist <= r.fuze {
+                    fuzed.push(*id);
+                    continue;
+                }
+
+                if r.burn > 0.0 && r.thrust > 0.0 {
+                    // how fast the line of sight is turning and closing, the navigation law wants
+                    // nav_gain times that turning as sideways accel, a skill of 0 just chases
+                    let los_rate = ((rx * rvy) - (ry * rvx)) / r2;
+                    let closing = (-((rx * rvx) + (ry * rvy)) / dist).max(LEAD_MIN_CLOSING);
+                    let lat = (r.lead * r.nav_gain * closing * los_rate).max(-r.thrust).min(r.thrust);
+                    // the rest of the thrust goes down the line of sight
+                    let along = ((r.thrust * r.thrust) - (lat * lat)).sqrt();
+                    let (ux, uy) = (rx / dist, ry / dist);
+                    let ax = (ux * along) - (uy * lat);
+                    let ay = (uy * along) + (ux * lat);
+                    want = Some((-ay).atan2(-ax));
+                }
+            }
+
+            let p = self.c_pos.get_mut(id).unwrap();
+            if let Some(wa) = want {
+                // the short way round
+                let pi = std::f64::consts::PI;
+                let da = ((wa - (p.a as f64)) + pi).rem_euclid(pi * 2.0) - pi;
+                let most = r.turn_rate * dt;
+                p.a += da.max(-most).min(most) as f32;
+            }
+
+            // do thrust
+            let d = self.c_dynamic.get_mut(id).unwrap();
+            if r.burn > 0.0 {
+                r.burn -= dt;
+                d.in_ax = -p.a.cos() as f64 * r.thrust;
+                d.in_ay = -p.a.sin() as f64 * r.thrust;
+            } else {
+                d.in_ax = 0.0;
+                d.in_ay = 0.0;
+            }
+        }
+
+        // close enough to the target goes off like hitting it would
+        for id in fuzed {
+            self.detonate(&id);
+        }
+    }
+
+    fn s_trail(&mut self, _dt: f64) {
+        for (id, t) in &mut self.c_trail {
+            match self.c_pos.get(&t.objid) {
+                Some(p) => {
+                    let px = p.x as f32;
+                    let py = p.y as f32;
+                    if t.pts.len() < 3 || (t.pts[1][0] - px).abs() > t.dist  || (t.pts[1][1] - py).abs() > t.dist {
+                        t.pts.insert(0, [px, py]);
+                        if t.pts.len() > t.max_len {
+                            t.pts.pop();
+                        }
+                    } else {
+                        t.pts[0][0] = px;
+                        t.pts[0][1] = py;
+                    }
+                },
+                None => {
+                    // object must have been deleted
+                    // we should go too
+                    self.entities.destroy(id);
+                }
+            }
+        }
+    }
+
+    fn s_predict(&mut self, dt: f64) {
+        let field = GravField{
+            gravs: &self.c_grav,
+            pos: &self.c_pos,
+            grid: if self.use_grid { Some(&self.grav_grid) } else { None },
+            tree: if self.barnes_hut { Some(&self.grav_tree) } else { None },
+        };
+        let col_grid = if self.use_grid { Some(&self.col_grid) } else { None };
+        let mut cands = Vec::new();
+
+        // for each dyn object for each gravity object in range
+        for (id, p) in &mut self.c_predictable {
+            // different rates for different items
+            p.till_next -= dt;
+            if p.till_next > 0.0 {
+                continue;
+            }
+            p.till_next = p.rate;
+
+            match self.c_dynamic.get(&p.objid) {
+                Some(obj) => {
+                    let objp = self.c_pos.get(&p.objid).expect("Predictables.objid must have pos");
+
+                    let mut fx = objp.x;
+                    let mut fy = objp.y;
+                    let mut fvx = obj.x_vel;
+                    let mut fvy = obj.y_vel;
+
+                    p.valid_len = 0;
+                    let mut hit_something = false;
+                    for pt in &mut p.pts {
+                        p.valid_len += 1;
+                        *pt = [fx as f32, fy as f32];
+
+                        //fill out the points
+                        if hit_something {
+                            break;
+                        }
+
+                        let accel = |x: f64, y: f64| field.accel(x, y, &IdVal::NONE);
+
+                        // the same steps s_move takes, so with no thrust the points are where it will be
+                        let (lx, ly) = (fx, fy);
+                        let steps = (p.tstep / SIM_DT).round() as usize;
+                        for _ in 0..steps {
+                            let (nx, ny, nvx, nvy) = integrate(
+                                self.integrator,
+                                &accel,
+                                fx, fy,
+                                fvx, fvy,
+                                0.0, 0.0,
+                                SIM_DT,
+                            );
+                            fx = nx;
+                            fy = ny;
+                            fvx = nvx;
+                            fvy = nvy;
+                        }
+
+                        if p.valid_len < 2 && (fvx == 0.0 || fvy == 0.0) {
+                            break;
+                        }
+
+                        if p.collidable {
+                            let cobj = self.c_collides.get(&p.objid).unwrap();
+                            // check for collision
+                            // check the whole stretch since the last point
+                            let (mx, my, half) = sweep_bounds(lx, ly, fx, fy);
+                            match col_grid {
+                                Some(grid) => grid.query_circle(mx, my, cobj.rad + half, &mut cands),
+                                None => {
+                                    cands.clear();
+                                    cands.extend(self.c_collider.keys());
+                                },
+                            }
+                            for cid in &cands {
+                                let col = match self.c_collider.get(cid) {
+                                    Some(col) => col,
+                                    None => continue,
+                                };
+                                if !col.stop_col {
+                                    continue
+                                }
+                                let cpos = &self.c_pos.get(cid).unwrap();
+                                let c = (cpos.x, cpos.y);
+                                if sweep_circles((lx, ly), (fx, fy), c, c, col.rad + cobj.rad).is_some() {
+                                    hit_something = true;
+                                }
+                            }
+                        }
+                    }
+                },
+                None => {
+                    // item must have been destroyed, and we should be too
+                    self.entities.destroy(id);
+                },
+            }
+        }
+    }
+
+    fn s_move(&mut self, dt: f64) {
+        let field = GravField{
+            gravs: &self.c_grav,
+            pos: &self.c_pos,
+            grid: if self.use_grid { Some(&self.grav_grid) } else { None },
+            tree: if self.barnes_hut { Some(&self.grav_tree) } else { None },
+        };
+
+        // for each dyn object for each gravity object in range
+        // everything moves from where it was at the start of the step
+        let mut moved = Vec::with_capacity(self.c_dynamic.len());
+        for (id, d) in &mut self.c_dynamic {
+            if d.still {
+                continue;
+            }
+            let p = &self.c_pos[id];
+
+            let accel = |x: f64, y: f64| field.accel(x, y, id);
+
+            let (nx, ny, nvx, nvy) = integrate(
+                self.integrator,
+                accel,
+                p.x, p.y,
+                d.x_vel, d.y_vel,
+                d.in_ax, d.in_ay,
+                dt,
+            );
+            d.x_vel = nvx;
+            d.y_vel = nvy;
+
+            moved.push((*id, nx, ny));
+
+            // do rotational vel to the rotation as well
+            //TODO
+        }
+
+        for (id, nx, ny) in moved {
+            let p = self.c_pos.get_mut(&id).unwrap();
+
+            p.x = nx;
+            p.y = ny;
+        }
+    }
+
+    fn s_collision(&mut self, dt: f64) {
+        let mut qe: Vec<(f64, f64, f32, u32)> = Vec::new();
+        let mut kills: Vec<(IdVal, u32)> = Vec::new();
+        let mut cands = Vec::new();
+        for (id, cobj) in &self.c_collides {
+            // check where it went this step against where the colliders went
+            // so fast things can't skip over something between steps
+            //TODO have different rates at which things check for collision?
+
+            let (x0, y0, x1, y1) = World::sweep(&self.prev_pos, &self.c_pos, id);
+            if self.use_grid {
+                let (mx, my, half) = sweep_bounds(x0, y0, x1, y1);
+                self.col_grid.query_circle(mx, my, cobj.rad + half, &mut cands);
+            } else {
+                cands.clear();
+                cands.extend(self.c_collider.keys());
+            }
+
+            // the first thing it touches
+            let mut hit: Option<(f64, IdVal)> = None;
+            for cid in &cands {
+                if *cid == *id {
+                    continue;
+                }
+                let c = match self.c_collider.get(cid) {
+                    Some(c) => c,
+                    None => continue,
+                };
+                let (cx0, cy0, cx1, cy1) = World::sweep(&self.prev_pos, &self.c_pos, cid);
+
+                if let Some(t) = sweep_circles((x0, y0), (x1, y1), (cx0, cy0), (cx1, cy1), c.rad + cobj.rad) {
+                    let first = match hit {
+                        Some((ht, hid)) => t < ht || (t == ht && *cid < hid),
+                        None => true,
+                    };
+                    if first {
+                        hit = Some((t, *cid));
+                    }
+                }
+            }
+
+            let (t, cid) = match hit {
+                Some(h) => h,
+                None => continue,
+            };
+            let cid = &cid;
+            let c = &self.c_collider[cid];
+
+            // where both were when they touched
+            let (cx0, cy0, cx1, cy1) = World::sweep(&self.prev_pos, &self.c_pos, cid);
+            let p = (x0 + ((x1 - x0) * t), y0 + ((y1 - y0) * t));
+            let colpos = (cx0 + ((cx1 - cx0) * t), cy0 + ((cy1 - cy0) * t));
+
+            // collided
+            match c.col_action {
+                CollisionType::Explosion(sz, delself) => {
+                    // explosions from an explosion are part of its chain, anything else starts a new one
+                    let chain = match self.c_explosion.get(cid) {
+                        Some(e) => e.chain,
+                        None => {
+                            self.next_chain += 1;
+                            self.next_chain
+                        },
+                    };
+
+                    // a ship with blast soaks explosions up in its hull, what hit it still goes off
+                    // but running into something solid is the end of it all the same
+                    let blast = self.c_ship.get_mut(id).filter(|s| s.blast > 0.0 && !c.stop_col);
+                    if let Some(s) = blast {
+                        if delself {
+                            qe.push((colpos.0, colpos.1, sz, chain));
+                            self.entities.destroy(cid);
+                        }
+                        s.hull -= s.blast * dt;
+                        if s.hull <= 0.0 && self.entities.destroy(id) {
+                            qe.push((p.0, p.1, self.tuning.hull_explosion as f32, chain));
+                            kills.push((*id, chain));
+                        }
+                        continue;
+                    }
+
+                    // queue spawn explosion
+                    let mut otherdie = false;
+                    // if the other collides had a collider
+                    // check if it wants an explosion as well
+                    if let Some(other_col) = self.c_collider.get(id) {
+                        if let CollisionType::Explosion(othersz, ds) = other_col.col_action {
+                            if ds { // if the otherone wanted to go out on a hit, it wants to explode
+                                qe.push((p.0, p.1, othersz, chain));
+                                otherdie = true;
+                            }
+                        }
+                    }
+
+                    if !otherdie || delself {
+                        if delself {
+                            qe.push((colpos.0, colpos.1, sz, chain));
+                        } else {
+                            qe.push((p.0, p.1, sz, chain));
+                        }
+                    }
+
+                    if delself {
+                        self.entities.destroy(cid);
+                    }
+                    if self.entities.destroy(id) {
+                        kills.push((*id, chain));
+                    }
+                },
+                CollisionType::FuelPup(amt) => {
+                    if let Some(ship) = &mut self.c_ship.get_mut(id) {
+                        ship.fuel += amt;
+                        self.entities.destroy(cid);
+                    }
+                },
+                CollisionType::Portal => {
+                    // enemy ships are ships too, but only the player gets out this way
+                    if self.playerid == Some(*id) {
+                        self.finished = true;
+                    }
+                },
+                CollisionType::None => (),
+            }
+        }
+        for (px, py, sz, chain) in qe {
+            let eid = self.spawn_explosion(px, py, sz, true);
+            self.c_explosion.get_mut(&eid).unwrap().chain = chain;
+        }
+
+        let t = &self.tuning;
+        for (id, chain) in kills {
+            if self.locks.contains(&id) {
+                self.score.locks += 1;
+                self.score.kill(t.score_lock, chain, t.score_combo);
+            } else if self.c_turret.contains_key(&id) {
+                self.score.turrets += 1;
+                self.score.kill(t.score_turret, chain, t.score_combo);
+            } else if let Some(pl) = self.c_pilot.get(&id) {
+                self.score.ships += 1;
+                self.score.kill(if pl.boss { t.score_boss } else { t.score_ship }, chain, t.score_combo);
+            }
+        }
+    }
+
+    // the player's ship faces away from the mouse, thrusts on the right button and fires on the left
+    fn s_player(&mut self, dt: f64) {
+        let pid = match self.playerid {
+            Some(id) => id,
+            None => return,
+        };
+
+        // TODO use angular accelaration to rotate, don't just snap to mouse
+        let p = &self.c_pos[&pid];
+        let aim = (p.y - self.input.my).atan2(p.x - self.input.mx) as f32;
+
+        let fire = self.input.lmb && self.c_ship[&pid].ammo > 0;
+        if fire {
+            self.input.lmb = false;
+        }
+        let cmd = ShipCmd{aim, thrust: self.input.rmb, fire, target: None, lead: 0.0};
+        self.fly_ship(&pid, &cmd, dt);
+    }
+
+    // enemy ships work out a command and fly it the same way the player does
+    // heading for a star beats running low on fuel, which beats chasing the player, which beats patrolling
+    fn s_pilot(&mut self, dt: f64) {
+        let field = GravField{
+            gravs: &self.c_grav,
+            pos: &self.c_pos,
+            grid: if self.use_grid { Some(&self.grav_grid) } else { None },
+            tree: if self.barnes_hut { Some(&self.grav_tree) } else { None },
+        };
+        let col_grid = if self.use_grid { Some(&self.col_grid) } else { None };
+        let (c_pos, c_dynamic) = (&self.c_pos, &self.c_dynamic);
+        let player = self.playerid.and_then(|id| Some((id, c_pos.get(&id)?, c_dynamic.get(&id)?)));
+
+        let mut cmds = Vec::new();
+        let mut lost = Vec::new(); // patrolling ships that got to their waypoint
+        for (id, pl) in &mut self.c_pilot {
+            let p = &self.c_pos[id];
+            let d = &self.c_dynamic[id];
+            let s = &self.c_ship[id];
+            pl.till_next_shot -= dt;
+
+            // where it ends up coasting for look seconds, anything solid in the way is trouble
+            let (gx, gy) = field.accel(p.x, p.y, id);
+            let t = pl.look;
+            let ex = p.x + (d.x_vel * t) + (0.5 * gx * t * t);
+            let ey = p.y + (d.y_vel * t) + (0.5 * gy * t * t);
+            let danger = World::raycast(
+                &self.c_collider, &self.c_pos, col_grid,
+                p.x, p.y,
+                ex, ey,
+                World::blocks_sight,
+            );
+
+            // the player counts as seen within sight with nothing solid between
+            let c_collider = &self.c_collider;
+            let seen = player.filter(|(_, pp, _)| {
+                let d2 = (pp.x - p.x).powi(2) + (pp.y - p.y).powi(2);
+                d2 < (pl.sight * pl.sight) && World::raycast(
+                    c_collider, c_pos, col_grid,
+                    p.x, p.y,
+                    pp.x, pp.y,
+                    World::blocks_sight,
+                ).is_none()
+            });
+
+            let mut want = None; // velocity it wants
+            let mut slack = PILOT_SLACK;
+            let mut fire = None;
+            if let Some(h) = danger {
+                // straight out from whatever it's heading for
+                pl.mode = PilotMode::Evade;
+                let sp = &self.c_pos[&h.id];
+                let (ax, ay) = (p.x - sp.x, p.y - sp.y);
+                let ad = ((ax * ax) + (ay * ay)).sqrt().max(1.0);
+                want = Some((d.x_vel + (ax / ad * pl.speed), d.y_vel + (ay / ad * pl.speed)));
+            } else if s.fuel < pl.flee_fuel {
+                // away from the player if it can see them, otherwise coast to save what's left
+                pl.mode = PilotMode::Flee;
+                if let Some((_, pp, _)) = seen {
+                    let (ax, ay) = (p.x - pp.x, p.y - pp.y);
+                    let ad = ((ax * ax) + (ay * ay)).sqrt().max(1.0);
+                    want = Some((ax / ad * pl.speed, ay / ad * pl.speed));
+                }
+            } else if let Some((pid, pp, pd)) = seen {
+                // match the player's velocity, closing in or backing off to keep its distance
+                pl.mode = PilotMode::Pursue;
+                let (ax, ay) = (pp.x - p.x, pp.y - p.y);
+                let ad = ((ax * ax) + (ay * ay)).sqrt().max(1.0);
+                let closing = (ad - pl.keep).max(-pl.speed).min(pl.speed);
+                want = Some((pd.x_vel + (ax / ad * closing), pd.y_vel + (ay / ad * closing)));
+
+                if ad < pl.range && pl.till_next_shot <= 0.0 && s.ammo > 0 {
+                    pl.till_next_shot = pl.fire_rate;
+                    fire = Some((pid, (-ay).atan2(-ax) as f32));
+                }
+            } else {
+                pl.mode = PilotMode::Patrol;
+                let (ax, ay) = (pl.waypoint.0 - p.x, pl.waypoint.1 - p.y);
+                let ad = ((ax * ax) + (ay * ay)).sqrt().max(1.0);
+                if ad < PILOT_ARRIVE {
+                    lost.push(*id);
+                }
+                // easing off on the way in, and not fussy about it so it doesn't burn all its fuel
+                let sp = (ad * 0.5).min(pl.cruise);
+                want = Some((ax / ad * sp, ay / ad * sp));
+                slack = slack.max(pl.cruise * 0.5);
+            }
+
+            // firing faces the player and doesn't thrust, otherwise thrust to make up the difference
+            // allowing for the gravity that'll pull on it meanwhile
+            let mut cmd = ShipCmd{aim: p.a, thrust: false, fire: false, target: None, lead: pl.skill};
+            if let Some((pid, a)) = fire {
+                cmd.aim = a;
+                cmd.fire = true;
+                cmd.target = Some(pid);
+            } else if let Some((wx, wy)) = want {
+                let dvx = wx - (d.x_vel + (gx * PILOT_GRAV_LEAD));
+                let dvy = wy - (d.y_vel + (gy * PILOT_GRAV_LEAD));
+                if ((dvx * dvx) + (dvy * dvy)) > (slack * slack) {
+                    cmd.aim = (-dvy).atan2(-dvx) as f32;
+                    cmd.thrust = true;
+                }
+            }
+            cmds.push((*id, cmd));
+        }
+
+        for id in lost {
+            let pl = self.c_pilot.get_mut(&id).unwrap();
+            let a = self.rng.gen_range(0.0, std::f64::consts::PI * 2.0);
+            let r = self.rng.gen_range(0.0, pl.patrol.max(1.0));
+            pl.waypoint = (pl.home.0 + (a.cos() * r), pl.home.1 + (a.sin() * r));
+        }
+        for (id, cmd) in cmds {
+            self.fly_ship(&id, &cmd, dt);
+        }
+    }
+
+    // turn to the command's aim, thrust and fire out the other way, burning fuel and mending the hull
+    pub fn fly_ship(&mut self, id: &IdVal, cmd: &ShipCmd, dt: f64) {
+        let p = self.c_pos.get_mut(id).unwrap();
+        p.a = cmd.aim;
+        let (px, py, pa) = (p.x, p.y, p.a);
+
+        let d = self.c_dynamic.get_mut(id).unwrap();
+        let s = self.c_ship.get_mut(id).unwrap();
+
+        d.in_ax = 0.0;
+        d.in_ay = 0.0;
+
+        let tamt = if s.fuel > 0.0 {
+            s.thrust
+        } else {
+            s.empty_thrust
+        };
+        //TODO taper thrust by mouse position
+        if cmd.thrust {
+            d.in_ax = -pa.cos() as f64 * tamt;
+            d.in_ay = -pa.sin() as f64 * tamt;
+        }
+
+        s.fuel -= (d.in_ax + d.in_ay).abs() * dt;
+        if s.fuel < 0.0 {
+            s.fuel = 0.0;
+        }
+        s.hull = (s.hull + (self.tuning.hull_regen * dt)).min(s.max_hull);
+
+        if !cmd.fire || s.ammo == 0 {
+            return;
+        }
+        s.ammo -= 1;
+
+        // out past the ship's edge so it doesn't hit its own shot
+        let nd = self.c_collides.get(id).map_or(0.0, |c| c.rad) + self.tuning.player_nuke_dist;
+        let pa_x = -pa.cos() as f64;
+        let pa_y = -pa.sin() as f64;
+        let npx = px + (pa_x * nd);
+        let npy = py + (pa_y * nd);
+        let nxv = d.x_vel + (pa_x * self.tuning.player_nuke_vel);
+        let nyv = d.y_vel + (pa_y * self.tuning.player_nuke_vel);
+        let shot = s.shot.clone();
+
+        let nid = self.spawn_nuke(&shot, npx, npy, pa, nxv, nyv, cmd.target);
+        if let Some(r) = self.c_rocket.get_mut(&nid) {
+            r.lead = cmd.lead;
+        }
+    }
+}
+
+// the MESHES entry for a mesh file name like "pnd", any case
+pub fn mesh_index(name: &str) -> Option<usize> {
+    return MESHES.iter().position(
+        |(p, _, _)| p.trim_start_matches('\\').trim_end_matches(".obj").eq_ignore_ascii_case(name)
+    );
+}
+
+// derive the seed for a level from the run seed
+// so a level comes out the same no matter what happened in the ones before it
+pub fn level_seed(seed: u64, level: usize) -> u64 {
+    // splitmix64 of the pair
+    let mut z = seed ^ (level as u64).wrapping_mul(0x9E3779B97F4A7C15);
+    z = z.wrapping_add(0x9E3779B97F4A7C15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58476D1CE4E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D049BB133111EB);
+    return z ^ (z >> 31);
+}
+
+// sort of parse a .obj file
+pub fn parse_mesh<R: BufRead>(f: R, scale: f32, color: [f32; 4]) -> MeshData {
+    let mut verts: Vec<MeshVert> = Vec::new();
+    let mut inds: Vec<u32> = Vec::new();
+
+    let mut uvs: Vec<[f32; 2]> = Vec::new();
+
+    let mut rad = 0.0;
+
+    for line in f.lines() {
+        if let Ok(l) = line {
+            let mut i = l.split_ascii_whitespace();
+            match i.next() {
+                Some("v") => {
+                    let x = i.next().unwrap();
+                    let y = i.next().unwrap();
+
+                    let x : f32 = x.parse::<f32>().unwrap() * scale;
+                    let y : f32 = y.parse::<f32>().unwrap() * scale;
+
+                    let r = ((x*x) + (y*y)).sqrt() as f32;
+                    if r > rad {
+                        rad = r;
+                    }
+
+                    let v = MeshVert{
+                        pos: [x, -y],
+                        uv: [0.0, 0.0],
+                        color: color,
+                    };
+
+                    verts.push(v);
+                },
+                Some("vt") => {
+                    let x = i.next().unwrap();
+                    let y = i.next().unwrap();
+
+                    let x : f32 = x.parse().unwrap();
+                    let y : f32 = y.parse().unwrap();
+
+                    uvs.push([x,y]);
+                },
+                Some("f") => {
+                    let mut i1 = i.next().unwrap().split("/");
+                    let i1_v : u32 = i1.next().unwrap().parse::<u32>().expect("Mesh doesn't have uvs") - 1;
+                    let i1_uv : u32 = i1.next().unwrap().parse::<u32>().unwrap() - 1;
+
+                    let mut i2 = i.next().unwrap().split("/");
+                    let i2_v : u32 = i2.next().unwrap().parse::<u32>().unwrap() - 1;
+                    let i2_uv : u32 = i2.next().unwrap().parse::<u32>().unwrap() - 1;
+
+                    let mut i3 = i.next().unwrap().split("/");
+                    let i3_v : u32 = i3.next().unwrap().parse::<u32>().unwrap() - 1;
+                    let i3_uv :u32 = i3.next().unwrap().parse::<u32>().unwrap() - 1;
+
+                    // add the indicies
+                    inds.push(i1_v);
+                    inds.push(i2_v);
+                    inds.push(i3_v);
+
+                    // associate uvs with positions
+                    verts[i1_v as usize].uv = uvs[i1_uv as usize];
+                    verts[i2_v as usize].uv = uvs[i2_uv as usize];
+                    verts[i3_v as usize].uv = uvs[i3_uv as usize];
+                },
+                _ => (),
+            }
+        }
+    }
+
+    return MeshData{
+        verts,
+        inds,
+        rad,
+    };
+}
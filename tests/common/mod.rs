@@ -0,0 +1,31 @@
+// what the integration tests share, a headless world and a made up pilot to fly it
+#![allow(dead_code)]
+
+use rusty_raid::world::*;
+
+pub const SEED: u64 = 1234;
+
+// made up mesh sizes, nothing here needs the real ones
+pub fn world() -> World {
+    let mut w = World::new(vec![20.0; MESHES.len()], SEED);
+    w.reset();
+    return w;
+}
+
+// tick i of a run, started on the first tick and then flying around in circles,
+// thrusting on and off and firing now and then
+pub fn fly(w: &mut World, i: usize) {
+    let (px, py) = w.playerid.and_then(|id| w.c_pos.get(&id)).map_or((0.0, 0.0), |p| (p.x, p.y));
+    let a = (i as f64) * 0.02;
+    w.input.mx = px + (300.0 * a.cos());
+    w.input.my = py + (300.0 * a.sin());
+    w.input.lmb = (i / 60) % 3 == 0;
+    w.input.rmb = i % 90 == 45;
+    w.input.reset = i == 0;
+}
+
+// everything that has to come out the same, where things are, what the rng got up to and the score
+pub fn end_state(w: &World) -> (Vec<(IdVal, f64, f64, f32)>, u64, usize, Option<usize>, u64, usize, f64) {
+    let pos: Vec<_> = w.c_pos.iter().map(|(id, p)| (*id, p.x, p.y, p.a)).collect();
+    return (pos, w.rng.draws(), w.level, w.lives, w.score.points, w.score.turrets, w.score.zone_time);
+}
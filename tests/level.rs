@@ -0,0 +1,41 @@
+use rusty_raid::level::LevelFile;
+
+mod common;
+use common::*;
+
+// the same file give or take rounding, turrets on moons have their place worked out again from the orbit
+fn near(a: &toml::Value, b: &toml::Value, key: &str) -> bool {
+    use toml::Value::*;
+    match (a, b) {
+        (Float(x), Float(y)) if key == "a" => {
+            let d = (x - y).rem_euclid(std::f64::consts::PI * 2.0);
+            return d.min((std::f64::consts::PI * 2.0) - d) < 1e-5;
+        },
+        (Float(x), Float(y)) => return (x - y).abs() <= (1e-9 * x.abs().max(1.0)),
+        (Array(x), Array(y)) => return x.len() == y.len() && x.iter().zip(y).all(|(x, y)| near(x, y, key)),
+        (Table(x), Table(y)) => return x.len() == y.len() && x.iter().all(|(k, v)| y.get(k).map_or(false, |w| near(v, w, k))),
+        _ => return a == b,
+    }
+}
+
+#[test]
+fn generated_zone_comes_back_from_its_level_file() {
+    for level in 0..4 {
+        let mut w = world();
+        let _ = w.gen_level(level);
+        let text = LevelFile::from_world(&w).to_string();
+
+        // parsing it gives the same file
+        let l = LevelFile::parse(&text).unwrap();
+        assert_eq!(l.to_string(), text);
+
+        // and loading it makes the same zone
+        let mut w2 = world();
+        w2.load_level(&l, level);
+        let again = LevelFile::from_world(&w2).to_string();
+        assert!(near(&text.parse().unwrap(), &again.parse().unwrap(), ""), "zone {} came back as\n{}", level, again);
+        assert_eq!(w2.locks.len(), w.locks.len());
+        assert_eq!(w2.c_turret.len(), w.c_turret.len());
+        assert_eq!(w2.c_pilot.len(), w.c_pilot.len());
+    }
+}
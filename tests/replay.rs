@@ -0,0 +1,43 @@
+use rusty_raid::world::*;
+use rusty_raid::replay::Replay;
+use rusty_raid::integrate::Integrator;
+use rusty_raid::barnes_hut::BH_THETA;
+
+mod common;
+use common::*;
+
+const TICKS: usize = 1200; // ten seconds
+
+#[test]
+fn replay_plays_back_the_same() {
+    let mut rec = world();
+    let mut r = Replay::new(SEED, Integrator::Verlet, Some(BH_THETA));
+    r.data = Some(rec.data_hash);
+    for i in 0..TICKS {
+        fly(&mut rec, i);
+        r.record(&rec, SIM_DT);
+        rec.step(SIM_DT);
+    }
+
+    let path = std::env::temp_dir().join(format!("rusty_raid_replay_{}.txt", std::process::id()));
+    r.save(&path).unwrap();
+    let mut r = Replay::load(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+    assert_eq!(r.ticks.len(), TICKS);
+    assert_eq!(r.data, Some(rec.data_hash));
+
+    let mut play = || {
+        r.pos = 0;
+        let mut w = world();
+        while let Some(dt) = r.play(&mut w) {
+            w.step(dt);
+        }
+        return end_state(&w);
+    };
+    let first = play();
+    let second = play();
+
+    assert!(first.1 > 0);
+    assert_eq!(first, end_state(&rec));
+    assert_eq!(first, second);
+}
@@ -0,0 +1,42 @@
+use rusty_raid::world::*;
+use rusty_raid::save::{Save, SAVE_VERSION};
+
+mod common;
+use common::*;
+
+const BEFORE: usize = 600; // ticks played before saving
+const AFTER: usize = 600; // and after
+
+#[test]
+fn restored_save_steps_the_same() {
+    let mut w = world();
+    for i in 0..BEFORE {
+        fly(&mut w, i);
+        w.step(SIM_DT);
+    }
+
+    let text = Save::from_world(&w).to_string();
+    let mut r = world();
+    Save::parse(&text).unwrap().restore(&mut r).unwrap();
+    assert_eq!(end_state(&r), end_state(&w));
+
+    for i in BEFORE..(BEFORE + AFTER) {
+        fly(&mut w, i);
+        w.step(SIM_DT);
+        fly(&mut r, i);
+        r.step(SIM_DT);
+    }
+    assert_eq!(end_state(&r), end_state(&w));
+}
+
+#[test]
+fn save_from_another_version_is_turned_away() {
+    let mut w = world();
+    w.new_run();
+    let text = Save::from_world(&w).to_string();
+    let old = text.replacen(&format!("version = {}", SAVE_VERSION), &format!("version = {}", SAVE_VERSION - 1), 1);
+    assert_ne!(old, text);
+
+    let e = Save::parse(&old).err().expect("an older save parsed");
+    assert!(e.to_string().contains("older"));
+}
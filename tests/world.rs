@@ -0,0 +1,159 @@
+use rusty_raid::world::*;
+
+mod common;
+use common::*;
+
+fn open_portal(w: &mut World) -> IdVal {
+    let id = w.add_portal(0.0, 0.0, 0.0);
+    w.c_collider.get_mut(&id).unwrap().col_action = CollisionType::Portal;
+    return id;
+}
+
+#[test]
+fn player_in_portal_finishes_zone() {
+    let mut w = world();
+    open_portal(&mut w);
+    w.spawn_player(0.0, 0.0);
+    w.step(SIM_DT);
+    assert!(w.finished);
+}
+
+#[test]
+fn enemy_in_portal_does_not_finish_zone() {
+    let mut w = world();
+    open_portal(&mut w);
+    w.spawn_player(5000.0, 0.0);
+    // just off the middle, right on top of all that mass the pull is too big to fly in
+    w.add_enemy("fighter", 10.0, 0.0).unwrap();
+    for _ in 0..10 {
+        w.step(SIM_DT);
+    }
+    assert!(!w.finished);
+    assert_eq!(w.level, 0);
+}
+
+#[test]
+fn level_without_locks_starts_open() {
+    let l = rusty_raid::level::LevelFile::parse("[portal]\nx = 0.0\ny = 0.0\n\n[spawn]\nx = 4000.0\ny = 0.0\n").unwrap();
+    let mut w = world();
+    w.load_level(&l, 0);
+    let portal = w.portal.unwrap();
+    assert!(matches!(w.c_collider[&portal].col_action, CollisionType::Portal));
+}
+
+#[test]
+fn zone_with_no_room_still_has_a_ship() {
+    // meshes so big nothing fits next to the portal, so every layout runs out of room for its locks
+    let mut w = World::new(vec![100000.0; MESHES.len()], SEED);
+    w.reset();
+    assert!(w.gen_level(0).is_err());
+    assert!(w.gen_error.is_some());
+    assert!(w.playerid.is_some());
+    let portal = w.portal.unwrap();
+    assert!(matches!(w.c_collider[&portal].col_action, CollisionType::Portal));
+}
+
+#[test]
+fn rocket_burns_out_after_its_target_goes() {
+    let mut w = world();
+    let target = w.add_fuel_powerup(5000.0, 0.0);
+    let o = rusty_raid::prefab::Overrides{target: Some(target), ..Default::default()};
+    let id = w.spawn_prefab("turret_nuke", (0.0, 0.0, 0.0), &o).unwrap();
+    w.step(SIM_DT);
+    assert!(w.c_dynamic[&id].in_ax != 0.0 || w.c_dynamic[&id].in_ay != 0.0);
+
+    w.despawn(&target);
+    let burn = w.c_rocket[&id].burn;
+    let steps = (burn / SIM_DT) as usize + 2;
+    for _ in 0..steps {
+        w.step(SIM_DT);
+    }
+    assert!(w.c_rocket[&id].target.is_none());
+    assert!(w.c_rocket[&id].burn <= 0.0);
+    assert_eq!((w.c_dynamic[&id].in_ax, w.c_dynamic[&id].in_ay), (0.0, 0.0));
+}
+
+#[test]
+fn prediction_matches_where_the_ship_goes() {
+    let mut w = world();
+    w.add_star(0.0, 0.0, 400.0, true);
+    w.spawn_player(1500.0, 0.0);
+    let pid = w.playerid.unwrap();
+    w.c_dynamic.get_mut(&pid).unwrap().y_vel = 400.0;
+    w.step(SIM_DT);
+
+    let pred = w.c_predictable.values().find(|p| p.objid == pid).unwrap();
+    let pts = pred.pts.clone();
+    let per = (pred.tstep / SIM_DT).round() as usize;
+
+    // coasting, no thrust, it should pass through every point on time
+    for n in 1..8 {
+        for _ in 0..per {
+            w.step(SIM_DT);
+        }
+        let p = &w.c_pos[&pid];
+        assert!(((p.x as f32) - pts[n][0]).abs() < 0.01, "point {} x {} predicted {}", n, p.x, pts[n][0]);
+        assert!(((p.y as f32) - pts[n][1]).abs() < 0.01, "point {} y {} predicted {}", n, p.y, pts[n][1]);
+    }
+}
+
+#[test]
+fn fast_ship_cant_jump_over_a_star() {
+    let mut w = world();
+    let star = w.add_star(0.0, 0.0, 20.0, false);
+    w.spawn_player(-500.0, 0.0);
+    let pid = w.playerid.unwrap();
+    // a thousand a step, from well clear on one side to well clear on the other
+    w.c_dynamic.get_mut(&pid).unwrap().x_vel = 1000.0 / SIM_DT;
+    w.step(SIM_DT);
+    assert!(!w.c_pos.contains_key(&pid));
+
+    // it goes up where it touched the star, not where it would have got to
+    let r = w.c_collider[&star].rad + 20.0;
+    let eid = w.c_explosion.keys().next().expect("no explosion");
+    let p = &w.c_pos[eid];
+    assert!((p.x + r).abs() < 1.0, "went off at {}, {}", p.x, p.y);
+    assert!(p.y.abs() < 1.0);
+}
+
+#[test]
+fn raycast_stops_at_the_first_solid_thing() {
+    let mut w = world();
+    let star = w.add_star(0.0, 0.0, 20.0, false);
+    let far = w.add_star(600.0, 0.0, 20.0, false);
+    let r = w.c_collider[&star].rad;
+
+    // passes right through a thin star, the one behind doesn't count
+    let hit = World::raycast(&w.c_collider, &w.c_pos, None, -1000.0, 0.0, 1000.0, 0.0, World::blocks_sight).unwrap();
+    assert_eq!(hit.id, star);
+    assert!((hit.dist - (1000.0 - r)).abs() < 1e-9);
+
+    // from the other side the far one is first
+    let hit = World::raycast(&w.c_collider, &w.c_pos, None, 1000.0, 0.0, -1000.0, 0.0, World::blocks_sight).unwrap();
+    assert_eq!(hit.id, far);
+
+    // just off to the side misses
+    assert!(World::raycast(&w.c_collider, &w.c_pos, None, -1000.0, r + 1.0, 1000.0, r + 1.0, World::blocks_sight).is_none());
+}
+
+#[test]
+fn raycast_from_inside_hits_at_0() {
+    let mut w = world();
+    let star = w.add_star(0.0, 0.0, 50.0, false);
+    let hit = World::raycast(&w.c_collider, &w.c_pos, None, 5.0, 0.0, 1000.0, 0.0, World::blocks_sight).unwrap();
+    assert_eq!(hit.id, star);
+    assert_eq!(hit.dist, 0.0);
+}
+
+#[test]
+fn only_solid_things_block_sight() {
+    let mut w = world();
+    let star = w.add_star(0.0, 0.0, 20.0, false);
+    let fuel = w.add_fuel_powerup(-500.0, 0.0);
+
+    // the pickup is in the way, but it doesn't stop anything
+    let hit = World::raycast(&w.c_collider, &w.c_pos, None, -1000.0, 0.0, 1000.0, 0.0, World::blocks_sight).unwrap();
+    assert_eq!(hit.id, star);
+    let hit = World::raycast(&w.c_collider, &w.c_pos, None, -1000.0, 0.0, 1000.0, 0.0, |_, _| true).unwrap();
+    assert_eq!(hit.id, fuel);
+}